@@ -0,0 +1,99 @@
+//! The allocator interface `Boks` is generic over.
+//!
+//! With the `nightly` feature this is just `std::alloc::Allocator`, so any allocator
+//! written against std works with `Boks`. On stable that trait doesn't exist yet,
+//! so a minimal stand-in with the same names and signatures is provided instead.
+
+#[cfg(feature = "nightly")]
+pub use std::alloc::{AllocError, Allocator, Global};
+
+#[cfg(not(feature = "nightly"))]
+pub use self::stable::{AllocError, Allocator, Global};
+
+#[cfg(not(feature = "nightly"))]
+mod stable {
+    use std::alloc::{self, Layout};
+    use std::error::Error;
+    use std::fmt;
+    use std::ptr::NonNull;
+
+    /// The allocator could not hand out the requested memory.
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    pub struct AllocError;
+
+    impl fmt::Display for AllocError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("memory allocation failed")
+        }
+    }
+
+    impl Error for AllocError {}
+
+    /// The subset of `std::alloc::Allocator` that `Boks` needs.
+    ///
+    /// # Safety
+    ///
+    /// Same contract as `std::alloc::Allocator`: memory handed out must stay valid
+    /// until it is deallocated or the allocator is dropped, and moving the
+    /// allocator must not invalidate it.
+    pub unsafe trait Allocator {
+        /// Hands out memory that fits `layout`. Never called with a zero size by `Boks`.
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError>;
+
+        /// Like [`Allocator::allocate`], but with every byte set to zero.
+        fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            let p = self.allocate(layout)?;
+            // SAFETY: allocate handed out p for at least layout.size() bytes
+            unsafe { p.cast::<u8>().write_bytes(0, p.len()) };
+            Ok(p)
+        }
+
+        /// Gives memory back.
+        ///
+        /// # Safety
+        ///
+        /// `ptr` must have been handed out by this allocator for this `layout` and
+        /// not been given back since.
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+    }
+
+    /// The global heap, what `std::alloc::alloc` hands out from.
+    #[derive(Copy, Clone, Default, Debug)]
+    pub struct Global;
+
+    unsafe impl Allocator for Global {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            debug_assert_ne!(layout.size(), 0);
+            // SAFETY: layout has a non-zero size
+            let p = NonNull::new(unsafe { alloc::alloc(layout) }).ok_or(AllocError)?;
+            Ok(NonNull::slice_from_raw_parts(p, layout.size()))
+        }
+
+        fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            debug_assert_ne!(layout.size(), 0);
+            // SAFETY: layout has a non-zero size
+            let p = NonNull::new(unsafe { alloc::alloc_zeroed(layout) }).ok_or(AllocError)?;
+            Ok(NonNull::slice_from_raw_parts(p, layout.size()))
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            // SAFETY: forwarded from our caller, ptr came from alloc with layout
+            unsafe { alloc::dealloc(ptr.as_ptr(), layout) }
+        }
+    }
+
+    unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            (**self).allocate(layout)
+        }
+
+        fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            (**self).allocate_zeroed(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            // SAFETY: forwarded from our caller
+            unsafe { (**self).deallocate(ptr, layout) }
+        }
+    }
+}
@@ -0,0 +1,544 @@
+//! A thread-safe reference-counted pointer, `Rk` with atomic counts.
+
+use crate::Boks;
+use crate::marker::PhantomOwns;
+use std::fmt;
+use std::hint;
+use std::marker::PhantomData;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ops::Deref;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::process;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+
+/// Past this the counts could overflow if enough threads keep cloning at once, so
+/// we give up like `Arc` does. Nobody gets here without leaking clones.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+/// The weak count while `get_mut` checks it is looking at the only pointer.
+const LOCKED: usize = usize::MAX;
+
+/// What an `Ark` points to, see `RkInner`.
+struct ArkInner<T> {
+    strong: AtomicUsize,
+    /// One more than the number of `Weak`s while there is any `Ark`.
+    weak: AtomicUsize,
+    value: ManuallyDrop<T>,
+}
+
+/// A pointer sharing ownership of a `T` across threads, like `Arc`.
+pub struct Ark<T> {
+    p: NonNull<ArkInner<T>>,
+    // See Rk, owning the ArkInner<T> would not tell the drop checker about T.
+    phantom: PhantomOwns<T>,
+}
+
+/// A pointer to the value of an `Ark` that doesn't keep it alive.
+pub struct Weak<T> {
+    p: NonNull<ArkInner<T>>,
+}
+
+// SAFETY: clones on other threads give out &T, so T has to be Sync, and the last
+// one to go, on whatever thread, drops the T, so T has to be Send as well. The
+// counts themselves are atomic.
+unsafe impl<T: Send + Sync> Send for Ark<T> {}
+
+// SAFETY: a &Ark can be cloned, so sharing one is as good as sending a clone.
+unsafe impl<T: Send + Sync> Sync for Ark<T> {}
+
+// SAFETY: a Weak can be upgraded into an Ark, so the same goes for it.
+unsafe impl<T: Send + Sync> Send for Weak<T> {}
+
+// SAFETY: as above.
+unsafe impl<T: Send + Sync> Sync for Weak<T> {}
+
+/// Like `Arc`, see `Rk`.
+impl<T: RefUnwindSafe> UnwindSafe for Ark<T> {}
+
+impl<T> Ark<T> {
+    pub fn new(value: T) -> Self {
+        let inner = Boks::ne(ArkInner {
+            strong: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+            value: ManuallyDrop::new(value),
+        });
+        Ark {
+            p: Boks::into_non_null(inner),
+            phantom: PhantomData,
+        }
+    }
+
+    /// A `Weak` pointing to the same value.
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        let weak = &this.inner().weak;
+        let mut n = weak.load(Ordering::Relaxed);
+        loop {
+            // Locked by get_mut on another clone, which is about to find out it is
+            // not the only one and let go.
+            if n == LOCKED {
+                hint::spin_loop();
+                n = weak.load(Ordering::Relaxed);
+                continue;
+            }
+            if n > MAX_REFCOUNT {
+                process::abort();
+            }
+            // Acquire pairs with the Release unlocking in get_mut, so a Weak made
+            // right after comes after whatever was done through the &mut T.
+            match weak.compare_exchange_weak(n, n + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return Weak { p: this.p },
+                Err(current) => n = current,
+            }
+        }
+    }
+
+    /// Only a snapshot, other threads may change it right after.
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.load(Ordering::Relaxed)
+    }
+
+    /// Only a snapshot, other threads may change it right after.
+    pub fn weak_count(this: &Self) -> usize {
+        match this.inner().weak.load(Ordering::Relaxed) {
+            // Locked by get_mut, which only succeeds if there is no Weak.
+            LOCKED => 0,
+            n => n - 1,
+        }
+    }
+
+    /// Whether both point to the same value, not just equal ones.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.p == other.p
+    }
+
+    /// A mutable reference to the value, if no other `Ark` or `Weak` points to it.
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if Self::is_unique(this) {
+            // SAFETY: nobody else can reach the value, and &mut this keeps it that way.
+            Some(unsafe { &mut (*this.p.as_ptr()).value })
+        } else {
+            None
+        }
+    }
+
+    /// Whether this is the only `Ark` and there is no `Weak`, like `Arc::is_unique`.
+    fn is_unique(this: &Self) -> bool {
+        let inner = this.inner();
+        // Reading weak and then strong on their own would race: another clone could
+        // downgrade after weak is read and drop itself before strong is, leaving a
+        // Weak that upgrades while we hand out a &mut T. So the weak count is
+        // locked, which stops downgrade, while strong is read. With weak at 1 the
+        // only way to a new Ark is cloning an existing one, which strong shows.
+        //
+        // Acquire pairs with the Release decrement of the last Weak that is gone,
+        // so whatever it did happened before we get to mutate the value.
+        if inner
+            .weak
+            .compare_exchange(1, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        // Acquire pairs with the Release decrements in drop_ref, for the same
+        // reason, with the clones that are gone.
+        let unique = inner.strong.load(Ordering::Acquire) == 1;
+        // Release pairs with the Acquire in downgrade.
+        inner.weak.store(1, Ordering::Release);
+        unique
+    }
+
+    /// Moves the value out if this is the only `Ark`, otherwise gives it back.
+    /// Any `Weak` left can no longer be upgraded.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        // Going from 1 to 0 atomically, so no Weak can upgrade in between.
+        if this
+            .inner()
+            .strong
+            .compare_exchange(1, 0, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(this);
+        }
+        // Same as in drop_ref, we are about to take the value.
+        atomic::fence(Ordering::Acquire);
+        let this = ManuallyDrop::new(this);
+        // SAFETY: this was the last Ark and it is never dropped, so the value is
+        // moved out exactly once and never dropped in place.
+        let value = unsafe { ptr::read(&*this.inner().value) };
+        drop(Weak { p: this.p });
+        Ok(value)
+    }
+
+    fn inner(&self) -> &ArkInner<T> {
+        // SAFETY: the memory stays allocated while there is an Ark, and the value
+        // stays initialised.
+        unsafe { self.p.as_ref() }
+    }
+
+    /// # Safety
+    ///
+    /// Only from drop, self is never used again.
+    unsafe fn drop_ref(&mut self) {
+        // Release so that everything this clone did with the value happens before
+        // the decrement, and so before the value is dropped by whoever goes last.
+        if self.inner().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        // Pairs with the Release decrements of all the other clones: we are the
+        // last, and now see everything they did before we drop the value.
+        atomic::fence(Ordering::Acquire);
+        // Released even if the value's destructor panics.
+        let _weak = Weak { p: self.p };
+        // SAFETY: this was the last Ark, nobody can reach the value anymore and
+        // it has not been dropped before.
+        unsafe { ptr::drop_in_place((&raw mut (*self.p.as_ptr()).value).cast::<T>()) };
+    }
+}
+
+impl<T> Weak<T> {
+    /// An `Ark` to the value, if it hasn't been dropped yet.
+    pub fn upgrade(&self) -> Option<Ark<T>> {
+        let strong = self.strong();
+        let mut n = strong.load(Ordering::Relaxed);
+        loop {
+            // Never back up from 0, the value may be getting dropped already.
+            if n == 0 {
+                return None;
+            }
+            if n > MAX_REFCOUNT {
+                process::abort();
+            }
+            // Acquire pairs with the Release in drop_ref, like for get_mut.
+            match strong.compare_exchange_weak(n, n + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => {
+                    return Some(Ark {
+                        p: self.p,
+                        phantom: PhantomData,
+                    });
+                }
+                Err(current) => n = current,
+            }
+        }
+    }
+
+    /// Only a snapshot, other threads may change it right after.
+    pub fn strong_count(&self) -> usize {
+        self.strong().load(Ordering::Relaxed)
+    }
+
+    // As for rk::Weak, only ever references to the counts.
+
+    fn strong(&self) -> &AtomicUsize {
+        // SAFETY: the memory stays allocated while there is a Weak, and the counts
+        // are always initialised.
+        unsafe { &(*self.p.as_ptr()).strong }
+    }
+
+    fn weak(&self) -> &AtomicUsize {
+        // SAFETY: as above.
+        unsafe { &(*self.p.as_ptr()).weak }
+    }
+
+    /// # Safety
+    ///
+    /// Only from drop, self is never used again.
+    unsafe fn drop_ref(&mut self) {
+        // Same as for the strong count: the last Weak frees the memory, so every
+        // other use of it has to happen before.
+        if self.weak().fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        atomic::fence(Ordering::Acquire);
+        // SAFETY: the memory came from Boks::into_non_null and this was the last
+        // pointer to it. The value is gone already, MaybeUninit makes sure the
+        // Boks only frees the memory.
+        drop(unsafe { Boks::from_non_null(self.p.cast::<MaybeUninit<ArkInner<T>>>()) });
+    }
+}
+
+/// `#[may_dangle]` for the same reason as on `Rk`.
+#[cfg(feature = "nightly")]
+unsafe impl<#[may_dangle] T> Drop for Ark<T> {
+    fn drop(&mut self) {
+        // SAFETY: this is drop, self is never used again
+        unsafe { self.drop_ref() }
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+impl<T> Drop for Ark<T> {
+    fn drop(&mut self) {
+        // SAFETY: this is drop, self is never used again
+        unsafe { self.drop_ref() }
+    }
+}
+
+#[cfg(feature = "nightly")]
+unsafe impl<#[may_dangle] T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        // SAFETY: this is drop, self is never used again
+        unsafe { self.drop_ref() }
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+impl<T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        // SAFETY: this is drop, self is never used again
+        unsafe { self.drop_ref() }
+    }
+}
+
+impl<T> Clone for Ark<T> {
+    fn clone(&self) -> Self {
+        // Relaxed is enough, this clone comes from an existing one, which keeps the
+        // value alive and was itself handed over to this thread properly.
+        if self.inner().strong.fetch_add(1, Ordering::Relaxed) > MAX_REFCOUNT {
+            process::abort();
+        }
+        Ark {
+            p: self.p,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        if self.weak().fetch_add(1, Ordering::Relaxed) > MAX_REFCOUNT {
+            process::abort();
+        }
+        Weak { p: self.p }
+    }
+}
+
+impl<T> Deref for Ark<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner().value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Ark<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("(Weak)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Oisann;
+    use crate::canary::Canary;
+    use crate::ledger::DropLedger;
+    use crate::live::LiveSet;
+    use crate::{assert_impl, assert_not_impl};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::Barrier;
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    const THREADS: usize = 8;
+    const ROUNDS: usize = 1000;
+
+    assert_impl!(Ark<u8>: Send, Sync, Unpin, UnwindSafe);
+    assert_impl!(Weak<u8>: Send, Sync);
+    // Unlike Boks, being Send isn't enough: every clone hands out a &T.
+    assert_not_impl!(Ark<Cell<u8>>: Send, Sync);
+    assert_not_impl!(Weak<Cell<u8>>: Send, Sync);
+    assert_not_impl!(Ark<Rc<u8>>: Send, Sync);
+
+    #[test]
+    fn single_threaded_basics() {
+        let set = LiveSet::new();
+        let mut a = Ark::new(set.track(1));
+        **Ark::get_mut(&mut a).unwrap() += 1;
+        let b = a.clone();
+        let w = Ark::downgrade(&a);
+        assert_eq!((Ark::strong_count(&a), Ark::weak_count(&a)), (2, 1));
+        assert!(Ark::get_mut(&mut a).is_none());
+        assert!(Ark::ptr_eq(&w.upgrade().unwrap(), &b));
+
+        let a = Ark::try_unwrap(a).unwrap_err();
+        drop(b);
+        let t = Ark::try_unwrap(a).unwrap();
+        assert_eq!(*t, 2);
+        assert!(w.upgrade().is_none());
+        assert_eq!(w.strong_count(), 0);
+        drop(t);
+        set.assert_clean();
+    }
+
+    #[test]
+    fn clones_dropped_across_threads_drop_the_value_once() {
+        let ledger = DropLedger::new();
+        let set = LiveSet::new();
+        for round in 0..ROUNDS / 10 {
+            let label = format!("value-{round}");
+            let a = Ark::new((
+                Oisann::with_ledger(round, &ledger, &label),
+                set.track(round),
+            ));
+            let barrier = Ark::new(Barrier::new(THREADS));
+            let handles: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    let a = a.clone();
+                    let barrier = barrier.clone();
+                    thread::spawn(move || {
+                        barrier.wait();
+                        for _ in 0..10 {
+                            drop(a.clone());
+                        }
+                        drop(a);
+                    })
+                })
+                .collect();
+            drop(a);
+            for h in handles {
+                h.join().unwrap();
+            }
+            ledger.assert_dropped_once(&label);
+        }
+        assert_eq!(ledger.len(), ROUNDS / 10);
+        set.assert_clean();
+    }
+
+    #[test]
+    fn upgrades_racing_the_last_drop() {
+        // Every upgrade that succeeds must see a value that is still alive, and the
+        // value must be dropped exactly once, whoever ends up last.
+        let set = LiveSet::new();
+        for _ in 0..ROUNDS / 10 {
+            let a = Ark::new((Canary::ne(), set.track(())));
+            let w = Ark::downgrade(&a);
+            let barrier = Ark::new(Barrier::new(THREADS + 1));
+            let handles: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    let w = w.clone();
+                    let barrier = barrier.clone();
+                    thread::spawn(move || {
+                        barrier.wait();
+                        for _ in 0..100 {
+                            match w.upgrade() {
+                                Some(a) => a.0.check(),
+                                None => break,
+                            }
+                        }
+                    })
+                })
+                .collect();
+            barrier.wait();
+            drop(a);
+            for h in handles {
+                h.join().unwrap();
+            }
+            assert!(w.upgrade().is_none());
+        }
+        set.assert_clean();
+    }
+
+    #[test]
+    fn writes_before_the_last_drop_are_seen_by_the_destructor() {
+        struct CheckOnDrop(Vec<AtomicBool>);
+
+        impl Drop for CheckOnDrop {
+            fn drop(&mut self) {
+                assert!(self.0.iter().all(|b| b.load(Ordering::Relaxed)));
+            }
+        }
+
+        for _ in 0..ROUNDS / 10 {
+            let a = Ark::new(CheckOnDrop(
+                (0..THREADS).map(|_| AtomicBool::new(false)).collect(),
+            ));
+            let handles: Vec<_> = (0..THREADS)
+                .map(|i| {
+                    let a = a.clone();
+                    // Relaxed on purpose, only the Release/Acquire in drop_ref orders
+                    // these before the destructor.
+                    thread::spawn(move || a.0[i].store(true, Ordering::Relaxed))
+                })
+                .collect();
+            drop(a);
+            for h in handles {
+                h.join().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn weak_clones_across_threads_free_once() {
+        let set = LiveSet::new();
+        let a = Ark::new(set.track(0));
+        let w = Ark::downgrade(&a);
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let w = w.clone();
+                thread::spawn(move || {
+                    for _ in 0..ROUNDS {
+                        drop(w.clone());
+                    }
+                })
+            })
+            .collect();
+        drop(a);
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert!(w.upgrade().is_none());
+        set.assert_clean();
+    }
+
+    #[test]
+    fn get_mut_racing_downgrade_and_drop() {
+        // The other thread downgrades its clone and drops it, so the strong count
+        // reaches 1 while a Weak exists. get_mut must not hand out a &mut T that
+        // overlaps with that Weak being upgraded: the other thread would see the
+        // value half way through being written.
+        for _ in 0..ROUNDS {
+            let mut a = Ark::new(AtomicBool::new(false));
+            let b = a.clone();
+            let done = Ark::new(AtomicBool::new(false));
+            let h = thread::spawn({
+                let done = done.clone();
+                move || {
+                    let w = Ark::downgrade(&b);
+                    drop(b);
+                    if let Some(b) = w.upgrade() {
+                        assert!(!b.load(Ordering::Relaxed), "get_mut and upgrade overlap");
+                    }
+                    done.store(true, Ordering::Release);
+                }
+            });
+            while !done.load(Ordering::Acquire) {
+                if let Some(v) = Ark::get_mut(&mut a) {
+                    *v.get_mut() = true;
+                    thread::yield_now();
+                    *v.get_mut() = false;
+                }
+                thread::yield_now();
+            }
+            h.join().unwrap();
+            assert!(Ark::get_mut(&mut a).is_some());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn ark_mutable_param() {
+        let mut y = 42;
+        let a = Ark::new(&mut y);
+        let _b = a.clone();
+        y += 1;
+        assert_eq!(y, 43);
+    }
+}
@@ -0,0 +1,101 @@
+//! Compile-time assertions about which traits a type implements.
+//!
+//! Mostly useful for auto traits, where a change to a field or a `PhantomData`
+//! marker silently changes whether a type is `Send`, `Sync`, `Unpin` and so on.
+
+/// Fails to compile unless the type implements every listed trait.
+///
+/// `assert_impl!(Boks<u8>: Send, Sync);`
+#[macro_export]
+macro_rules! assert_impl {
+    ($ty:ty: $($trait:path),+ $(,)?) => {
+        $(
+            const _: fn() = || {
+                fn assert_impl<T: ?Sized + $trait>() {}
+                assert_impl::<$ty>();
+            };
+        )+
+    };
+}
+
+/// Fails to compile if the type implements any of the listed traits.
+///
+/// `assert_not_impl!(Boks<Rc<u8>>: Send, Sync);`
+///
+/// There is no negative trait bound, so this relies on method resolution: the
+/// blanket impl below is always there, and a second one joins it when `$ty`
+/// implements the trait, which makes picking `some_item` ambiguous.
+#[macro_export]
+macro_rules! assert_not_impl {
+    ($ty:ty: $($trait:path),+ $(,)?) => {
+        $(
+            const _: fn() = || {
+                trait AmbiguousIfImpl<A> {
+                    fn some_item() {}
+                }
+
+                impl<T: ?Sized> AmbiguousIfImpl<()> for T {}
+
+                #[allow(dead_code)]
+                struct Invalid;
+
+                impl<T: ?Sized + $trait> AmbiguousIfImpl<Invalid> for T {}
+
+                let _ = <$ty as AmbiguousIfImpl<_>>::some_item;
+            };
+        )+
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::allocator::{AllocError, Global};
+    use crate::{Boks, Discard, Empty, Oisann};
+    use std::cell::Cell;
+    use std::marker::PhantomPinned;
+    use std::panic::{RefUnwindSafe, UnwindSafe};
+    use std::rc::Rc;
+
+    // Boks owns its T, so Send and Sync follow T (see the unsafe impls), and it
+    // is always Unpin because moving it never moves the T.
+    assert_impl!(Boks<u8>: Send, Sync, Unpin, UnwindSafe, RefUnwindSafe);
+    assert_impl!(Boks<&mut i32>: Send, Sync, Unpin);
+    assert_impl!(Boks<PhantomPinned>: Unpin);
+    assert_impl!(Boks<Cell<u8>>: Send);
+    assert_not_impl!(Boks<Cell<u8>>: Sync);
+    assert_not_impl!(Boks<Rc<u8>>: Send, Sync);
+    assert_not_impl!(Boks<*mut u8>: Send, Sync);
+    assert_not_impl!(Boks<&mut i32>: UnwindSafe);
+    // UnwindSafe follows T like for Box, not what the NonNull<T> alone would give.
+    assert_impl!(Boks<Cell<u8>>: UnwindSafe);
+    assert_not_impl!(Boks<Cell<u8>>: RefUnwindSafe);
+    assert_impl!(Boks<str>: UnwindSafe, RefUnwindSafe);
+    assert_not_impl!(Boks<dyn std::any::Any>: UnwindSafe, RefUnwindSafe);
+
+    // Oisann holds its T by value, so it has exactly the auto traits of T.
+    assert_impl!(Oisann<u8>: Send, Sync, Unpin, UnwindSafe, RefUnwindSafe);
+    assert_impl!(Oisann<Cell<u8>>: Send, UnwindSafe);
+    assert_not_impl!(Oisann<Cell<u8>>: Sync, RefUnwindSafe);
+    assert_not_impl!(Oisann<Rc<u8>>: Send, Sync);
+    assert_not_impl!(Oisann<PhantomPinned>: Unpin);
+    assert_not_impl!(Oisann<&mut i32>: UnwindSafe);
+
+    // Empty only has a PhantomData<fn() -> T>, and fn pointers implement every
+    // auto trait whatever T is. PhantomData<T> would have copied them from T.
+    assert_impl!(Empty<u8>: Send, Sync, Unpin, UnwindSafe, RefUnwindSafe);
+    assert_impl!(Empty<Rc<u8>>: Send, Sync);
+    assert_impl!(Empty<*mut u8>: Send, Sync);
+    assert_impl!(Empty<Cell<u8>>: Sync, RefUnwindSafe);
+    assert_impl!(Empty<PhantomPinned>: Unpin);
+    assert_impl!(Empty<&mut i32>: UnwindSafe);
+    // Same for the traits written out by hand, none of them asks anything of T.
+    assert_impl!(Empty<String>: Copy, Default, std::fmt::Debug, DoubleEndedIterator, ExactSizeIterator, std::iter::FusedIterator);
+
+    // Discard is built the same way, on PhantomContravariant<T>, that is
+    // PhantomData<fn(*const T)>.
+    assert_impl!(Discard<Rc<u8>>: Send, Sync, Unpin, UnwindSafe, RefUnwindSafe);
+    assert_impl!(Discard<String>: Copy, Default, std::fmt::Debug, std::io::Write);
+
+    assert_impl!(Global: Send, Sync, Unpin, UnwindSafe, RefUnwindSafe);
+    assert_impl!(AllocError: Send, Sync, Unpin, UnwindSafe, RefUnwindSafe);
+}
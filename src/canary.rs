@@ -0,0 +1,169 @@
+//! Catching reads of values that were already dropped.
+
+use std::fmt;
+use std::ptr;
+
+const ALIVE: u64 = 0x00C0_FFEE_CAFE_F00D;
+const DEAD: u64 = 0xDEAD_DEAD_DEAD_DEAD;
+
+/// A value that knows whether it has been dropped, optionally watching another one.
+///
+/// Dropping a canary overwrites its magic value, so reading it afterwards through
+/// a pointer that outlived it panics instead of quietly seeing stale data. The
+/// same goes for a freed `Boks`, which is [poisoned](crate::POISON) in debug builds.
+///
+/// A canary watching another, `Canary<'a>` holding a `&'a Canary<'a>`, is the shape
+/// the drop checker cares about: with `#[may_dangle]` its container may be dropped
+/// after the watched canary is gone, and [`check`](Canary::check) catches any
+/// destructor that reads it anyway.
+pub struct Canary<'a> {
+    magic: u64,
+    peer: Option<&'a Canary<'a>>,
+}
+
+impl<'a> Canary<'a> {
+    pub fn ne() -> Self {
+        Canary {
+            magic: ALIVE,
+            peer: None,
+        }
+    }
+
+    /// A canary that also checks `peer` whenever it is read.
+    pub fn watching(peer: &'a Canary<'a>) -> Self {
+        Canary {
+            magic: ALIVE,
+            peer: Some(peer),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        // Volatile so the check is not optimised out based on the canary never
+        // having been dropped as far as the compiler is concerned.
+        // SAFETY: magic is a plain u64 we have a reference to.
+        unsafe { ptr::read_volatile(&self.magic) == ALIVE }
+    }
+
+    /// Panics unless this canary, and the one it is watching, are both still alive.
+    #[track_caller]
+    pub fn check(&self) {
+        assert!(self.is_alive(), "Canary read after drop");
+        if let Some(peer) = self.peer {
+            assert!(
+                peer.is_alive(),
+                "Canary read its peer after the peer was dropped"
+            );
+        }
+    }
+
+    /// The canary this one is watching, after checking both.
+    #[track_caller]
+    pub fn peer(&self) -> Option<&'a Canary<'a>> {
+        self.check();
+        self.peer
+    }
+}
+
+impl Default for Canary<'_> {
+    fn default() -> Self {
+        Self::ne()
+    }
+}
+
+impl fmt::Debug for Canary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Canary")
+            .field("alive", &self.is_alive())
+            .field("watching", &self.peer.is_some())
+            .finish()
+    }
+}
+
+impl Canary<'_> {
+    fn die(&mut self) {
+        assert!(self.is_alive(), "Canary dropped twice");
+        // Volatile so the write survives even though the memory is about to be
+        // given back.
+        // SAFETY: magic is a plain u64 we have a mutable reference to.
+        unsafe { ptr::write_volatile(&mut self.magic, DEAD) };
+    }
+}
+
+/// Never reads `peer`, so with `#[may_dangle]` a canary may outlive the one it
+/// watches as long as nobody reads it.
+#[cfg(feature = "nightly")]
+unsafe impl<#[may_dangle] 'a> Drop for Canary<'a> {
+    fn drop(&mut self) {
+        self.die();
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Drop for Canary<'_> {
+    fn drop(&mut self) {
+        self.die();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::ManuallyDrop;
+
+    #[test]
+    fn alive_until_dropped() {
+        let watched = Canary::ne();
+        let watcher = Canary::watching(&watched);
+        watcher.check();
+        assert!(watcher.peer().unwrap().is_alive());
+
+        let mut c = ManuallyDrop::new(Canary::ne());
+        // SAFETY: c is not dropped again, only looked at, and its memory stays valid.
+        unsafe { ManuallyDrop::drop(&mut c) };
+        assert!(!c.is_alive());
+    }
+
+    #[test]
+    #[should_panic(expected = "Canary read after drop")]
+    fn read_after_drop_panics() {
+        let mut c = ManuallyDrop::new(Canary::ne());
+        // SAFETY: the memory stays valid, reading it is what is being tested.
+        unsafe { ManuallyDrop::drop(&mut c) };
+        c.check();
+    }
+
+    #[test]
+    #[should_panic(expected = "Canary read its peer after the peer was dropped")]
+    fn dead_peer_panics() {
+        let mut watched = ManuallyDrop::new(Canary::ne());
+        // SAFETY: as above.
+        unsafe { ManuallyDrop::drop(&mut watched) };
+        let watcher = Canary::watching(&watched);
+        watcher.check();
+    }
+
+    #[test]
+    #[should_panic(expected = "Canary dropped twice")]
+    fn double_drop_panics() {
+        let mut c = ManuallyDrop::new(Canary::ne());
+        // SAFETY: as above, dropping twice is what is being tested.
+        unsafe {
+            ManuallyDrop::drop(&mut c);
+            ManuallyDrop::drop(&mut c);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn boks_keeps_the_eyepatch_promise() {
+        use crate::Boks;
+
+        let watcher;
+        let watched = Canary::ne();
+        watcher = Boks::ne(Canary::watching(&watched));
+        watcher.check();
+        // watched is dropped first. Both Boks and Canary have #[may_dangle], so
+        // this compiles, and neither destructor reads the dead peer, or the
+        // canary would panic.
+    }
+}
@@ -0,0 +1,130 @@
+//! How an `Oisann` turns its value into text when it is dropped.
+
+use std::any;
+use std::fmt;
+
+/// A way of formatting a `T`, picked when the `Oisann` is built.
+pub trait Format<T: ?Sized> {
+    fn fmt(&self, value: &T, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+/// Formats with `{:?}`, what `Oisann::ne` uses.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DebugFormat;
+
+impl<T: ?Sized + fmt::Debug> Format<T> for DebugFormat {
+    fn fmt(&self, value: &T, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(value, f)
+    }
+}
+
+/// Formats with `{}`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DisplayFormat;
+
+impl<T: ?Sized + fmt::Display> Format<T> for DisplayFormat {
+    fn fmt(&self, value: &T, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(value, f)
+    }
+}
+
+/// Only writes the name of the type, so it works for any `T` and never looks
+/// at the value.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TypeNameFormat;
+
+impl<T: ?Sized> Format<T> for TypeNameFormat {
+    fn fmt(&self, _: &T, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(any::type_name::<T>())
+    }
+}
+
+/// Formats with a user closure.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FnFormat<C>(pub C);
+
+impl<T: ?Sized, C> Format<T> for FnFormat<C>
+where
+    C: Fn(&T, &mut fmt::Formatter<'_>) -> fmt::Result,
+{
+    fn fmt(&self, value: &T, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.0)(value, f)
+    }
+}
+
+/// Pairs a value with its format so it can go through `format_args!`.
+pub(crate) struct Formatted<'a, T: ?Sized, F>(pub(crate) &'a T, pub(crate) &'a F);
+
+impl<T: ?Sized, F: Format<T>> fmt::Display for Formatted<'_, T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.1.fmt(self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Oisann;
+    use crate::ledger::DropLedger;
+    use crate::sink::ThreadLocalSink;
+
+    /// Implements neither Debug nor Display.
+    struct Opaque;
+
+    #[test]
+    fn debug_and_display() {
+        drop(Oisann::ne("hei"));
+        drop(Oisann::display("hei"));
+        drop(Oisann::with_format("hei", ThreadLocalSink, DebugFormat));
+        drop(Oisann::with_format("hei", ThreadLocalSink, DisplayFormat));
+        assert_eq!(ThreadLocalSink::take(), ["\"hei\"", "hei"]);
+    }
+
+    #[test]
+    fn type_name_traces_any_type() {
+        drop(Oisann::with_format(Opaque, ThreadLocalSink, TypeNameFormat));
+        drop(Oisann::with_format(
+            vec![1u8],
+            ThreadLocalSink,
+            TypeNameFormat,
+        ));
+        assert_eq!(
+            ThreadLocalSink::take(),
+            ["drop_check::format::tests::Opaque", "alloc::vec::Vec<u8>"]
+        );
+        drop(Oisann::type_name(Opaque));
+    }
+
+    #[test]
+    fn closure_format() {
+        let ledger = DropLedger::new();
+        let o = Oisann::with_format(
+            Opaque,
+            ledger.sink("opaque"),
+            FnFormat(|_: &Opaque, f: &mut fmt::Formatter<'_>| f.write_str("<opaque>")),
+        );
+        drop(o);
+        assert_eq!(ledger.events()[0].value, "<opaque>");
+
+        drop(Oisann::with_fn([1, 2, 3], |v, f| {
+            write!(f, "{} items", v.len())
+        }));
+    }
+
+    #[test]
+    fn generic_holders_need_no_bounds() {
+        // With T: Debug on the struct, Holder would have to repeat that bound, and so
+        // would every impl and function mentioning Holder<T>.
+        struct Holder<T>(#[allow(dead_code)] Oisann<T, ThreadLocalSink, TypeNameFormat>);
+
+        fn hold<T>(t: T) -> Holder<T> {
+            Holder(Oisann::with_format(t, ThreadLocalSink, TypeNameFormat))
+        }
+
+        drop(hold(Opaque));
+        assert_eq!(
+            ThreadLocalSink::take(),
+            ["drop_check::format::tests::Opaque"]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Recording drops so tests can assert on them instead of reading stdout.
+
+use crate::sink::DropSink;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+use std::time::Instant;
+
+/// One recorded drop.
+#[derive(Clone, Debug)]
+pub struct DropEvent {
+    /// What the dropped value was attached to the ledger as.
+    pub label: String,
+    /// The value, formatted just before it was dropped.
+    pub value: String,
+    /// The thread the drop ran on.
+    pub thread: ThreadId,
+    /// Position of this drop among all drops recorded by the ledger, from 0.
+    pub seq: usize,
+    /// When the drop was recorded.
+    pub at: Instant,
+}
+
+/// A thread-safe log of drops, shared by cloning.
+///
+/// Attach values with [`Oisann::with_ledger`](crate::Oisann::with_ledger) or any
+/// other [`DropSink`] user through [`DropLedger::sink`], then
+/// check what was dropped and in which order once they are gone.
+#[derive(Clone, Default)]
+pub struct DropLedger {
+    events: Arc<Mutex<Vec<DropEvent>>>,
+}
+
+impl DropLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a drop of `value` under `label`, from the current thread.
+    pub fn record(&self, label: impl Into<String>, value: impl Into<String>) {
+        let mut events = self.lock();
+        let seq = events.len();
+        events.push(DropEvent {
+            label: label.into(),
+            value: value.into(),
+            thread: thread::current().id(),
+            seq,
+            at: Instant::now(),
+        });
+    }
+
+    /// All drops recorded so far, in the order they happened.
+    pub fn events(&self) -> Vec<DropEvent> {
+        self.lock().clone()
+    }
+
+    /// The labels of all drops recorded so far, in the order they happened.
+    pub fn labels(&self) -> Vec<String> {
+        self.lock().iter().map(|e| e.label.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// A sink recording every announcement it gets under `label`.
+    pub fn sink(&self, label: impl Into<String>) -> LedgerSink {
+        LedgerSink {
+            ledger: self.clone(),
+            label: label.into(),
+        }
+    }
+
+    /// Forgets every recorded drop, sequence numbers start from 0 again.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Panics unless exactly these labels were dropped, in exactly this order.
+    #[track_caller]
+    pub fn assert_order<I>(&self, expected: I)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|l| l.as_ref().to_owned())
+            .collect();
+        let actual = self.labels();
+        assert!(
+            actual == expected,
+            "drops happened in the wrong order\n  expected: {expected:?}\n    actual: {actual:?}"
+        );
+    }
+
+    /// Panics unless `label` was dropped exactly once.
+    #[track_caller]
+    pub fn assert_dropped_once(&self, label: &str) {
+        let count = self.lock().iter().filter(|e| e.label == label).count();
+        assert!(
+            count == 1,
+            "expected `{label}` to be dropped once, but it was dropped {count} times\n{self:?}"
+        );
+    }
+
+    /// Panics if `label` was dropped at all.
+    #[track_caller]
+    pub fn assert_not_dropped(&self, label: &str) {
+        let events = self.lock();
+        if let Some(event) = events.iter().find(|e| e.label == label) {
+            panic!("expected `{label}` not to be dropped, but it was: {event:?}");
+        }
+    }
+
+    /// A value that panicked while formatting could poison the lock, the events
+    /// recorded before that are still fine to read.
+    fn lock(&self) -> MutexGuard<'_, Vec<DropEvent>> {
+        self.events.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl fmt::Debug for DropLedger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.lock().iter().map(|e| (&e.label, &e.value)))
+            .finish()
+    }
+}
+
+/// Records into a [`DropLedger`] under a fixed label, see [`DropLedger::sink`].
+#[derive(Clone, Debug)]
+pub struct LedgerSink {
+    ledger: DropLedger,
+    label: String,
+}
+
+impl DropSink for LedgerSink {
+    fn announce(&mut self, value: fmt::Arguments<'_>) {
+        self.ledger.record(self.label.clone(), value.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Boks, Oisann};
+    use std::thread;
+
+    #[test]
+    fn records_values_and_order() {
+        let ledger = DropLedger::new();
+        {
+            let _outer = Oisann::with_ledger(1, &ledger, "outer");
+            let _inner = Oisann::with_ledger("hei", &ledger, "inner");
+        }
+        // Locals are dropped in reverse order of declaration.
+        ledger.assert_order(["inner", "outer"]);
+
+        let events = ledger.events();
+        assert_eq!(events[0].value, "\"hei\"");
+        assert_eq!(events[1].value, "1");
+        assert_eq!(events.iter().map(|e| e.seq).collect::<Vec<_>>(), [0, 1]);
+        assert!(events[0].at <= events[1].at);
+    }
+
+    #[test]
+    fn boks_drops_its_value_with_it() {
+        let ledger = DropLedger::new();
+        let b = Boks::ne(Oisann::with_ledger(42, &ledger, "boxed"));
+        assert!(ledger.is_empty());
+        drop(b);
+        ledger.assert_dropped_once("boxed");
+    }
+
+    #[test]
+    fn struct_fields_drop_in_declaration_order() {
+        struct Pair {
+            _first: Oisann<i32, LedgerSink>,
+            _second: Boks<Oisann<i32, LedgerSink>>,
+        }
+
+        let ledger = DropLedger::new();
+        let pair = Pair {
+            _second: Boks::ne(Oisann::with_ledger(2, &ledger, "second")),
+            _first: Oisann::with_ledger(1, &ledger, "first"),
+        };
+        drop(pair);
+        ledger.assert_order(["first", "second"]);
+    }
+
+    #[test]
+    fn vec_drops_front_to_back() {
+        let ledger = DropLedger::new();
+        let v: Vec<_> = (0..3)
+            .map(|i| Oisann::with_ledger(i, &ledger, format!("item-{i}")))
+            .collect();
+        drop(v);
+        ledger.assert_order(["item-0", "item-1", "item-2"]);
+    }
+
+    #[test]
+    fn records_drops_from_other_threads() {
+        let ledger = DropLedger::new();
+        let o = Oisann::with_ledger(7, &ledger, "moved");
+        let worker = thread::spawn(move || drop(o));
+        let worker_id = worker.thread().id();
+        worker.join().unwrap();
+
+        let events = ledger.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].thread, worker_id);
+        assert_ne!(events[0].thread, thread::current().id());
+    }
+
+    #[test]
+    fn forgotten_values_are_not_recorded() {
+        let ledger = DropLedger::new();
+        std::mem::forget(Oisann::with_ledger(1, &ledger, "forgotten"));
+        ledger.assert_not_dropped("forgotten");
+    }
+
+    #[test]
+    #[should_panic(expected = "drops happened in the wrong order")]
+    fn assert_order_panics_on_mismatch() {
+        let ledger = DropLedger::new();
+        ledger.record("a", "1");
+        ledger.record("b", "2");
+        ledger.assert_order(["b", "a"]);
+    }
+}
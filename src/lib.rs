@@ -1,22 +1,446 @@
-#![feature(dropck_eyepatch)]
+#![cfg_attr(
+    feature = "nightly",
+    feature(
+        allocator_api,
+        coerce_unsized,
+        dropck_eyepatch,
+        pin_coerce_unsized_trait,
+        unsize
+    )
+)]
 
-use std::fmt::Debug;
+pub mod allocator;
+pub mod ark;
+mod auto_traits;
+pub mod canary;
+pub mod format;
+pub mod ledger;
+pub mod live;
+pub mod marker;
+pub mod rk;
+pub mod sink;
+mod variance;
+pub mod vek;
+
+use crate::allocator::{AllocError, Allocator, Global};
+use crate::format::{DebugFormat, DisplayFormat, FnFormat, Format, Formatted, TypeNameFormat};
+use crate::ledger::{DropLedger, LedgerSink};
+use crate::marker::{PhantomContravariant, PhantomCovariant, PhantomOwns};
+use crate::sink::{DropSink, PrintSink};
+use std::alloc::{Layout, handle_alloc_error};
+use std::borrow::{Borrow, BorrowMut};
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::iter::FusedIterator;
 use std::marker::PhantomData;
-use std::ptr::NonNull;
+#[cfg(feature = "nightly")]
+use std::marker::Unsize;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+#[cfg(feature = "nightly")]
+use std::ops::CoerceUnsized;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::pin::Pin;
+#[cfg(feature = "nightly")]
+use std::pin::PinCoerceUnsized;
+use std::ptr::{self, NonNull};
 
-pub struct Boks<T> {
+pub struct Boks<T: ?Sized, A: Allocator = Global> {
     p: NonNull<T>,
-    phantom: PhantomData<T>,
+    alloc: A,
+    phantom: PhantomOwns<T>,
 }
 
 impl<T> Boks<T> {
     pub fn ne(t: T) -> Self {
+        Self::new_in(t, Global)
+    }
+
+    /// Like [`Boks::ne`], but hands the error back instead of aborting when the
+    /// heap is out of memory. `t` is dropped in that case.
+    pub fn try_new(t: T) -> Result<Self, AllocError> {
+        Self::try_new_in(t, Global)
+    }
+
+    /// Places `t` on the heap and pins it there, see [`Boks::into_pin`].
+    pub fn pin(t: T) -> Pin<Self> {
+        Boks::into_pin(Boks::ne(t))
+    }
+
+    /// Allocates room for a `T` on the heap without initialising it, so large
+    /// values can be built in place instead of on the stack.
+    pub fn new_uninit() -> Boks<MaybeUninit<T>> {
+        Self::new_uninit_in(Global)
+    }
+
+    /// Like [`Boks::new_uninit`], but hands the error back instead of aborting
+    /// when the heap is out of memory.
+    pub fn try_new_uninit() -> Result<Boks<MaybeUninit<T>>, AllocError> {
+        Self::try_new_uninit_in(Global)
+    }
+
+    /// Allocates room for a `T` on the heap with every byte set to zero.
+    pub fn new_zeroed() -> Boks<MaybeUninit<T>> {
+        Self::new_zeroed_in(Global)
+    }
+
+    /// Allocates room for `len` values of `T` on the heap without initialising them.
+    pub fn new_uninit_slice(len: usize) -> Boks<[MaybeUninit<T>]> {
+        Self::new_uninit_slice_in(len, Global)
+    }
+
+    /// Allocates room for `len` values of `T` on the heap with every byte set to zero.
+    pub fn new_zeroed_slice(len: usize) -> Boks<[MaybeUninit<T>]> {
+        Self::new_zeroed_slice_in(len, Global)
+    }
+}
+
+impl<T, A: Allocator> Boks<MaybeUninit<T>, A> {
+    /// Initialises the value in place and returns the now initialised Boks.
+    pub fn write(mut b: Self, t: T) -> Boks<T, A> {
+        b.write(t);
+        // SAFETY: the value was just initialised
+        unsafe { b.assume_init() }
+    }
+
+    /// Converts to `Boks<T, A>` without touching the allocation.
+    ///
+    /// # Safety
+    ///
+    /// The value must be fully initialised, see [`MaybeUninit::assume_init`].
+    pub unsafe fn assume_init(self) -> Boks<T, A> {
+        let (p, alloc) = Boks::into_non_null_with_allocator(self);
+        // SAFETY: MaybeUninit<T> has the same layout as T, and the caller guarantees
+        // the value is initialised.
+        unsafe { Boks::from_non_null_in(p.cast(), alloc) }
+    }
+}
+
+impl<T, A: Allocator> Boks<[MaybeUninit<T>], A> {
+    /// Converts to `Boks<[T], A>` without touching the allocation.
+    ///
+    /// # Safety
+    ///
+    /// Every element must be fully initialised, see [`MaybeUninit::assume_init`].
+    pub unsafe fn assume_init(self) -> Boks<[T], A> {
+        let (p, alloc) = Boks::into_non_null_with_allocator(self);
+        // SAFETY: [MaybeUninit<T>] has the same layout as [T] and the cast keeps the
+        // length, and the caller guarantees every element is initialised.
+        unsafe { Boks::from_non_null_in(NonNull::slice_from_raw_parts(p.cast(), p.len()), alloc) }
+    }
+}
+
+/// Hands out memory for `layout` from `alloc`, optionally zeroed.
+///
+/// Zero-sized layouts never reach the allocator: any well aligned non-null pointer
+/// will do, and Drop knows not to hand it back.
+fn allocate_in<A: Allocator>(
+    layout: Layout,
+    zeroed: bool,
+    alloc: &A,
+) -> Result<NonNull<u8>, AllocError> {
+    if layout.size() == 0 {
+        return Ok(layout.dangling_ptr());
+    }
+    let p = if zeroed {
+        alloc.allocate_zeroed(layout)?
+    } else {
+        alloc.allocate(layout)?
+    };
+    Ok(p.cast())
+}
+
+impl<T, A: Allocator> Boks<T, A> {
+    /// Places `t` in memory handed out by `alloc`, the Boks then keeps `alloc`
+    /// around so it can give the memory back when dropped.
+    pub fn new_in(t: T, alloc: A) -> Self {
+        Boks::write(Boks::new_uninit_in(alloc), t)
+    }
+
+    /// Like [`Boks::new_in`], but hands the error back instead of aborting when
+    /// `alloc` is out of memory. `t` is dropped in that case.
+    pub fn try_new_in(t: T, alloc: A) -> Result<Self, AllocError> {
+        Ok(Boks::write(Boks::try_new_uninit_in(alloc)?, t))
+    }
+
+    /// Allocates room for a `T` in `alloc` without initialising it.
+    pub fn new_uninit_in(alloc: A) -> Boks<MaybeUninit<T>, A> {
+        match Boks::try_new_uninit_in(alloc) {
+            Ok(b) => b,
+            Err(_) => handle_alloc_error(Layout::new::<T>()),
+        }
+    }
+
+    /// Like [`Boks::new_uninit_in`], but hands the error back instead of aborting
+    /// when `alloc` is out of memory.
+    pub fn try_new_uninit_in(alloc: A) -> Result<Boks<MaybeUninit<T>, A>, AllocError> {
+        let p = allocate_in(Layout::new::<T>(), false, &alloc)?;
+        // SAFETY: p is valid for a T, and MaybeUninit<T> needs no initialisation
+        Ok(unsafe { Boks::from_non_null_in(p.cast(), alloc) })
+    }
+
+    /// Allocates room for a `T` in `alloc` with every byte set to zero.
+    pub fn new_zeroed_in(alloc: A) -> Boks<MaybeUninit<T>, A> {
+        let layout = Layout::new::<T>();
+        match allocate_in(layout, true, &alloc) {
+            // SAFETY: p is valid for a T, and MaybeUninit<T> needs no initialisation
+            Ok(p) => unsafe { Boks::from_non_null_in(p.cast(), alloc) },
+            Err(_) => handle_alloc_error(layout),
+        }
+    }
+
+    /// Allocates room for `len` values of `T` in `alloc` without initialising them.
+    pub fn new_uninit_slice_in(len: usize, alloc: A) -> Boks<[MaybeUninit<T>], A> {
+        let layout = Layout::array::<T>(len).expect("capacity overflow");
+        match allocate_in(layout, false, &alloc) {
+            // SAFETY: p is valid for len values of T, and MaybeUninit<T> needs no
+            // initialisation
+            Ok(p) => unsafe {
+                Boks::from_non_null_in(NonNull::slice_from_raw_parts(p.cast(), len), alloc)
+            },
+            Err(_) => handle_alloc_error(layout),
+        }
+    }
+
+    /// Allocates room for `len` values of `T` in `alloc` with every byte set to zero.
+    pub fn new_zeroed_slice_in(len: usize, alloc: A) -> Boks<[MaybeUninit<T>], A> {
+        let layout = Layout::array::<T>(len).expect("capacity overflow");
+        match allocate_in(layout, true, &alloc) {
+            // SAFETY: p is valid for len values of T, and MaybeUninit<T> needs no
+            // initialisation
+            Ok(p) => unsafe {
+                Boks::from_non_null_in(NonNull::slice_from_raw_parts(p.cast(), len), alloc)
+            },
+            Err(_) => handle_alloc_error(layout),
+        }
+    }
+
+    /// Moves the value out of the Boks and frees the allocation.
+    pub fn into_inner(b: Self) -> T {
+        let (p, alloc) = Boks::into_non_null_with_allocator(b);
+        // SAFETY: p points to a valid T that nobody else owns, and since the Boks
+        // is gone it will not be read or dropped through p again.
+        let t = unsafe { p.as_ptr().read() };
+        // The value has just been moved out, so only the memory is left to free.
+        drop(Free {
+            p: p.cast(),
+            layout: Layout::new::<T>(),
+            alloc: &alloc,
+        });
+        t
+    }
+}
+
+impl<T: ?Sized> Boks<T> {
+    /// Takes ownership of a pointer previously returned by [`Boks::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `raw` must have come from `Boks::into_raw` (or `Box::into_raw`) and must not
+    /// have been passed to `from_raw` since, as the new Boks will drop the value
+    /// and free the memory as if it had been its own `p` all along.
+    pub unsafe fn from_raw(raw: *mut T) -> Self {
+        // SAFETY: the caller guarantees raw came from into_raw, which never returns null
+        unsafe { Self::from_non_null(NonNull::new_unchecked(raw)) }
+    }
+
+    /// Takes ownership of a pointer previously returned by [`Boks::into_non_null`].
+    ///
+    /// # Safety
+    ///
+    /// Same as [`Boks::from_raw`].
+    pub unsafe fn from_non_null(p: NonNull<T>) -> Self {
+        // SAFETY: forwarded from our caller, Global is what into_non_null allocated with
+        unsafe { Self::from_non_null_in(p, Global) }
+    }
+
+    /// Gives up ownership and returns the `p` the Boks was holding, without
+    /// dropping the value or freeing the memory.
+    ///
+    /// Use [`Boks::from_raw`] to turn it back into a Boks, otherwise both leak.
+    pub fn into_raw(b: Self) -> *mut T {
+        Boks::into_non_null(b).as_ptr()
+    }
+
+    /// Like [`Boks::into_raw`], but keeps the non-null guarantee in the type.
+    pub fn into_non_null(b: Self) -> NonNull<T> {
+        Boks::into_non_null_with_allocator(b).0
+    }
+}
+
+impl<T: ?Sized, A: Allocator> Boks<T, A> {
+    /// Takes ownership of `p`, which lives in memory handed out by `alloc`.
+    ///
+    /// # Safety
+    ///
+    /// `p` must point to a valid `T` that was allocated in `alloc` with the layout
+    /// of that value, like the pointer returned by [`Boks::into_non_null_with_allocator`],
+    /// and nobody else may own it.
+    pub unsafe fn from_non_null_in(p: NonNull<T>, alloc: A) -> Self {
         Self {
-            // SAFETY: Box::into_raw always return a pointer
-            p: unsafe { NonNull::new_unchecked(Box::into_raw(Box::new(t))) },
+            p,
+            alloc,
             phantom: PhantomData,
         }
     }
+
+    /// Gives up ownership and returns `p` together with the allocator it lives in.
+    pub fn into_non_null_with_allocator(b: Self) -> (NonNull<T>, A) {
+        let b = ManuallyDrop::new(b);
+        // SAFETY: b is wrapped in ManuallyDrop and never used again, so alloc is
+        // moved out exactly once.
+        let alloc = unsafe { ptr::read(&b.alloc) };
+        (b.p, alloc)
+    }
+
+    /// Leaks the Boks and returns a reference to the value that lives as long as
+    /// the allocator does, which for `Global` is `'static`.
+    pub fn leak<'a>(b: Self) -> &'a mut T
+    where
+        A: 'a,
+    {
+        let (mut p, alloc) = Boks::into_non_null_with_allocator(b);
+        // The allocator has to stay alive for the memory to stay valid.
+        mem::forget(alloc);
+        // SAFETY: p points to a valid T that nothing will ever free, and the Boks
+        // it came from is gone so this is the only reference to it.
+        unsafe { p.as_mut() }
+    }
+
+    /// Returns the allocator backing `b`.
+    ///
+    /// This is an associated function so it doesn't shadow a method on `T`.
+    pub fn allocator(b: &Self) -> &A {
+        &b.alloc
+    }
+
+    /// Pins the value where it already is on the heap.
+    ///
+    /// Moving the Boks only moves `p`, never the value it points to, so nothing
+    /// else is needed for the pinning guarantees as long as the Boks can't hand
+    /// out the value by move. `A: 'static` makes sure the memory can't be reused
+    /// behind our back either: if the Boks is leaked, the allocator is too.
+    pub fn into_pin(b: Self) -> Pin<Self>
+    where
+        A: 'static,
+    {
+        // SAFETY: the value behind p never moves while the Boks is alive, and
+        // Pin<Boks> gives out no way to move it out, see the note above.
+        unsafe { Pin::new_unchecked(b) }
+    }
+}
+
+impl<T: ?Sized, A: Allocator + 'static> From<Boks<T, A>> for Pin<Boks<T, A>> {
+    fn from(b: Boks<T, A>) -> Self {
+        Boks::into_pin(b)
+    }
+}
+
+// SAFETY: Deref and DerefMut always hand out the same p, so coercing a pinned
+// Boks to an unsized one keeps pointing at the same pinned value.
+#[cfg(feature = "nightly")]
+unsafe impl<T: ?Sized, A: Allocator> PinCoerceUnsized for Boks<T, A> {}
+
+// SAFETY: Boks owns its T like Box does, so sending the Boks sends the T, and the
+// allocator goes along with it. NonNull alone would make Boks neither Send nor Sync.
+unsafe impl<T: ?Sized + Send, A: Allocator + Send> Send for Boks<T, A> {}
+
+// SAFETY: a &Boks only gives out &T and &A, so sharing it is sharing those.
+unsafe impl<T: ?Sized + Sync, A: Allocator + Sync> Sync for Boks<T, A> {}
+
+/// Catching a panic with a Boks in hand can only expose a broken `T` if `T` itself
+/// could be, like `Box` only `T` and the allocator matter. Without these the
+/// `NonNull<T>` would make `Boks<T>` `UnwindSafe` only when `T` is `RefUnwindSafe`.
+impl<T: ?Sized + UnwindSafe, A: Allocator + UnwindSafe> UnwindSafe for Boks<T, A> {}
+
+impl<T: ?Sized + RefUnwindSafe, A: Allocator + RefUnwindSafe> RefUnwindSafe for Boks<T, A> {}
+
+/// Moving a Boks never moves the value it owns, so the pointer itself is `Unpin`
+/// whatever `T` is. Pinning `T` goes through `Pin<Boks<T>>` instead.
+impl<T: ?Sized, A: Allocator> Unpin for Boks<T, A> {}
+
+/// Takes over the allocation of a `Box`, which is also how unsized values such as
+/// `Boks<str>` can be built without going through a sized `Boks` first.
+#[cfg(feature = "nightly")]
+impl<T: ?Sized, A: Allocator> From<Box<T, A>> for Boks<T, A> {
+    fn from(b: Box<T, A>) -> Self {
+        let (p, alloc) = Box::into_non_null_with_allocator(b);
+        // SAFETY: Box allocates exactly the way Boks does, so it can take over p
+        unsafe { Self::from_non_null_in(p, alloc) }
+    }
+}
+
+/// Takes over the allocation of a `Box`, which is also how unsized values such as
+/// `Boks<str>` or `Boks<dyn Trait>` can be built on stable.
+#[cfg(not(feature = "nightly"))]
+impl<T: ?Sized> From<Box<T>> for Boks<T> {
+    fn from(b: Box<T>) -> Self {
+        // SAFETY: Box::into_raw never returns null, and Box allocates from the
+        // global heap exactly the way Boks does, so it can take over the pointer.
+        unsafe { Self::from_raw(Box::into_raw(b)) }
+    }
+}
+
+/// Lets `Boks<[T; N]>` coerce to `Boks<[T]>` and `Boks<Foo>` to `Boks<dyn Trait>`,
+/// the pointer picks up the length or vtable as metadata during the coercion.
+#[cfg(feature = "nightly")]
+impl<T: ?Sized + Unsize<U>, U: ?Sized, A: Allocator> CoerceUnsized<Boks<U, A>> for Boks<T, A> {}
+
+impl<T: ?Sized, A: Allocator> Boks<T, A> {
+    /// Drops the value and frees its memory, shared by both `Drop` impls below.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called from `drop`, the Boks can't be used afterwards.
+    unsafe fn drop_and_free(&mut self) {
+        // SAFETY: p points to a valid T that has not been dropped yet. Only the size
+        // and alignment are read here, which for unsized T come from the pointer
+        // metadata, so nothing T borrows is touched.
+        let layout = Layout::for_value(unsafe { self.p.as_ref() });
+        // Frees the memory even if T's destructor panics. Nothing drops the T
+        // again after that: drop_in_place has already dropped whatever it could,
+        // and the Boks itself is never dropped twice.
+        let _free = Free {
+            p: self.p.cast(),
+            layout,
+            alloc: &self.alloc,
+        };
+        // SAFETY: we own the T behind p and nobody can observe it after this.
+        // drop_in_place does not count as accessing T for the eyepatch, it only
+        // runs T's own destructor, which dropck checks separately through PhantomOwns<T>.
+        unsafe { ptr::drop_in_place(self.p.as_ptr()) };
+    }
+}
+
+/// The byte a Boks fills its memory with before freeing it, in debug builds.
+///
+/// Anything still reading through a dangling pointer then sees garbage that is
+/// easy to spot, like a [`Canary`](crate::canary::Canary) that is no longer alive,
+/// rather than the old value looking fine until the allocator reuses the memory.
+pub const POISON: u8 = 0xDD;
+
+/// Deallocates `p` when dropped, see `Boks::drop_and_free`.
+///
+/// Whatever lived in the memory must already be dropped or moved out.
+struct Free<'a, A: Allocator> {
+    p: NonNull<u8>,
+    layout: Layout,
+    alloc: &'a A,
+}
+
+impl<A: Allocator> Drop for Free<'_, A> {
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            if cfg!(debug_assertions) {
+                // SAFETY: p is valid for layout.size() bytes until it is freed below.
+                unsafe { ptr::write_bytes(self.p.as_ptr(), POISON, self.layout.size()) };
+            }
+            // SAFETY: p was allocated in alloc with this same layout and has not
+            // been freed since. Zero-sized values were never allocated.
+            unsafe { self.alloc.deallocate(self.p, self.layout) };
+        }
+    }
 }
 
 /// Without `#[may_dangle]`: the drop checker requires `T` to still be valid
@@ -24,62 +448,491 @@ impl<T> Boks<T> {
 ///
 /// With `#[may_dangle]`: `T` is allowed to be logically dropped before `drop`,
 /// because the destructor promises not to access `T`.
-unsafe impl<#[may_dangle] T> Drop for Boks<T> {
+///
+/// `A` has no `#[may_dangle]`: the destructor does use the allocator to free the
+/// memory, so anything the allocator borrows must still be alive at that point.
+#[cfg(feature = "nightly")]
+unsafe impl<#[may_dangle] T: ?Sized, A: Allocator> Drop for Boks<T, A> {
     fn drop(&mut self) {
-        // SAFETY: p was constructed from a box and has not been freed since.
-        unsafe {
-            Box::from_raw(self.p.as_ptr());
-        }
+        // SAFETY: this is drop, self is never used again
+        unsafe { self.drop_and_free() }
     }
 }
 
-impl<T> std::ops::Deref for Boks<T> {
+/// Stable fallback without the eyepatch: the drop checker has to assume this
+/// destructor accesses `T`, so anything `T` borrows must outlive the Boks, just
+/// like for any other type with a `Drop` impl.
+#[cfg(not(feature = "nightly"))]
+impl<T: ?Sized, A: Allocator> Drop for Boks<T, A> {
+    fn drop(&mut self) {
+        // SAFETY: this is drop, self is never used again
+        unsafe { self.drop_and_free() }
+    }
+}
+
+impl<T: ?Sized, A: Allocator> std::ops::Deref for Boks<T, A> {
     type Target = T;
     fn deref(&self) -> &Self::Target {
-        // SAFETY: is valid since it was constructed from a valid T, and turned into a pointer
-        // through Box which creates aligned pointer and hasn't been freed as self is not dropped
-        unsafe { &*self.p.as_ref() }
+        // SAFETY: is valid since it was constructed from a valid T, in memory allocated
+        // with the layout of T and hasn't been freed as self is not dropped
+        unsafe { self.p.as_ref() }
     }
 }
 
-impl<T> std::ops::DerefMut for Boks<T> {
+impl<T: ?Sized, A: Allocator> std::ops::DerefMut for Boks<T, A> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        // SAFETY: is valid since it was constructed from a valid T, and turned into a pointer
-        // through Box which creates aligned pointer and hasn't been freed as self is not dropped
+        // SAFETY: is valid since it was constructed from a valid T, in memory allocated
+        // with the layout of T and hasn't been freed as self is not dropped
         // As we have a mut reference means no other immutable and mutable reference given.
-        unsafe { &mut *self.p.as_mut() }
+        unsafe { self.p.as_mut() }
     }
 }
 
-pub struct Oisann<T: Debug>(T);
+impl<T: Clone, A: Allocator + Clone> Clone for Boks<T, A> {
+    fn clone(&self) -> Self {
+        Boks::new_in((**self).clone(), self.alloc.clone())
+    }
+
+    /// Clones into the value we already own, so the allocation is reused.
+    fn clone_from(&mut self, source: &Self) {
+        (**self).clone_from(&**source);
+    }
+}
+
+impl<T: Clone, A: Allocator + Clone> Clone for Boks<[T], A> {
+    fn clone(&self) -> Self {
+        let mut b = Boks::<T, A>::new_uninit_slice_in(self.len(), self.alloc.clone());
+        let mut written = Written {
+            slots: &mut b,
+            len: 0,
+        };
+        for (slot, t) in written.slots.iter_mut().zip(self.iter()) {
+            slot.write(t.clone());
+            written.len += 1;
+        }
+        mem::forget(written);
+        // SAFETY: every element was written above.
+        unsafe { b.assume_init() }
+    }
+
+    /// Clones element by element when the lengths match, so the allocation is
+    /// reused, like `Box<[T]>` does.
+    fn clone_from(&mut self, source: &Self) {
+        if self.len() == source.len() {
+            self.clone_from_slice(source);
+        } else {
+            *self = source.clone();
+        }
+    }
+}
+
+/// Drops the clones written so far if a later clone panics. The
+/// `Boks<[MaybeUninit<T>]>` they are in frees the memory, but never drops them.
+struct Written<'a, T> {
+    slots: &'a mut [MaybeUninit<T>],
+    len: usize,
+}
+
+impl<T> Drop for Written<'_, T> {
+    fn drop(&mut self) {
+        let written: *mut [MaybeUninit<T>] = &mut self.slots[..self.len];
+        // SAFETY: the first len slots were initialised and nothing else drops them.
+        unsafe { ptr::drop_in_place(written as *mut [T]) };
+    }
+}
+
+impl<A: Allocator + Clone> Clone for Boks<str, A> {
+    fn clone(&self) -> Self {
+        let mut bytes = Boks::<u8, A>::new_uninit_slice_in(self.len(), self.alloc.clone());
+        // SAFETY: both are valid for len bytes, and they are different allocations.
+        unsafe { ptr::copy_nonoverlapping(self.as_ptr(), bytes.as_mut_ptr().cast(), self.len()) };
+        // SAFETY: every byte was copied from a str, so they are initialised UTF-8.
+        unsafe { Boks::from_utf8_unchecked(bytes.assume_init()) }
+    }
+}
+
+impl<A: Allocator> Boks<str, A> {
+    /// Like `str::from_boxed_utf8_unchecked`.
+    ///
+    /// # Safety
+    ///
+    /// The bytes must be valid UTF-8.
+    unsafe fn from_utf8_unchecked(bytes: Boks<[u8], A>) -> Self {
+        let (p, alloc) = Boks::into_non_null_with_allocator(bytes);
+        // SAFETY: str has the same layout as [u8] and the cast keeps the length,
+        // and the caller guarantees the bytes are UTF-8.
+        unsafe { Boks::from_non_null_in(NonNull::new_unchecked(p.as_ptr() as *mut str), alloc) }
+    }
+}
+
+impl<T: ?Sized + PartialEq, A: Allocator> PartialEq for Boks<T, A> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: ?Sized + Eq, A: Allocator> Eq for Boks<T, A> {}
+
+impl<T: ?Sized + PartialOrd, A: Allocator> PartialOrd for Boks<T, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+
+    fn lt(&self, other: &Self) -> bool {
+        **self < **other
+    }
+
+    fn le(&self, other: &Self) -> bool {
+        **self <= **other
+    }
+
+    fn gt(&self, other: &Self) -> bool {
+        **self > **other
+    }
+
+    fn ge(&self, other: &Self) -> bool {
+        **self >= **other
+    }
+}
+
+impl<T: ?Sized + Ord, A: Allocator> Ord for Boks<T, A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+/// Hashes the same as `T`, which together with `Borrow<T>` lets maps keyed by
+/// `Boks<T>` be looked up with a plain `&T`.
+impl<T: ?Sized + Hash, A: Allocator> Hash for Boks<T, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+impl<T: ?Sized + Debug, A: Allocator> Debug for Boks<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display, A: Allocator> fmt::Display for Boks<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+/// Prints the address of the value, not of the Boks itself.
+impl<T: ?Sized, A: Allocator> fmt::Pointer for Boks<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&self.p, f)
+    }
+}
+
+impl<T: Default> Default for Boks<T> {
+    fn default() -> Self {
+        Boks::ne(T::default())
+    }
+}
+
+/// An empty slice, which doesn't allocate.
+impl<T> Default for Boks<[T]> {
+    fn default() -> Self {
+        // SAFETY: there are no elements to initialise.
+        unsafe { Boks::<T>::new_uninit_slice(0).assume_init() }
+    }
+}
+
+/// An empty string, which doesn't allocate.
+impl Default for Boks<str> {
+    fn default() -> Self {
+        // SAFETY: no bytes are valid UTF-8.
+        unsafe { Boks::from_utf8_unchecked(Boks::default()) }
+    }
+}
+
+impl<T> From<T> for Boks<T> {
+    fn from(t: T) -> Self {
+        Boks::ne(t)
+    }
+}
+
+impl<T: ?Sized, A: Allocator> AsRef<T> for Boks<T, A> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized, A: Allocator> AsMut<T> for Boks<T, A> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+impl<T: ?Sized, A: Allocator> Borrow<T> for Boks<T, A> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized, A: Allocator> BorrowMut<T> for Boks<T, A> {
+    fn borrow_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+pub struct Oisann<T, S = PrintSink, F = DebugFormat> {
+    value: T,
+    sink: S,
+    format: F,
+    // The S: DropSink and F: Format<T> bounds are only checked by the constructors,
+    // which store the matching `announce` here. A Drop impl has to repeat every
+    // bound of the struct, so with them on the struct every type holding an
+    // Oisann<T> would have to spell out T: Debug too. Erased to `()` so it doesn't
+    // make Oisann contravariant in T.
+    announce: unsafe fn(NonNull<()>),
+}
+
+/// # Safety
+///
+/// `this` must point to a live `Oisann<T, S, F>`, not borrowed elsewhere.
+unsafe fn announce<T, S: DropSink, F: Format<T>>(this: NonNull<()>) {
+    // SAFETY: guaranteed by the caller.
+    let this = unsafe { this.cast::<Oisann<T, S, F>>().as_mut() };
+    this.sink
+        .announce(format_args!("{}", Formatted(&this.value, &this.format)));
+}
 
 impl<T: Debug> Oisann<T> {
     pub fn ne(t: T) -> Self {
-        Oisann(t)
+        Oisann::with_sink(t, PrintSink)
+    }
+
+    /// Records the drop in `ledger` under `label` instead of printing it.
+    pub fn with_ledger(
+        t: T,
+        ledger: &DropLedger,
+        label: impl Into<String>,
+    ) -> Oisann<T, LedgerSink> {
+        Oisann::with_sink(t, ledger.sink(label))
+    }
+}
+
+impl<T: Debug, S: DropSink> Oisann<T, S> {
+    /// Announces the drop to `sink` instead of printing it.
+    pub fn with_sink(t: T, sink: S) -> Self {
+        Oisann::with_format(t, sink, DebugFormat)
+    }
+}
+
+impl<T: fmt::Display> Oisann<T, PrintSink, DisplayFormat> {
+    /// Prints the value with `{}` instead of `{:?}`.
+    pub fn display(t: T) -> Self {
+        Oisann::with_format(t, PrintSink, DisplayFormat)
     }
 }
 
-impl<T: Debug> Drop for Oisann<T> {
+impl<T> Oisann<T, PrintSink, TypeNameFormat> {
+    /// Prints only the name of `T`, for values that can't be formatted.
+    pub fn type_name(t: T) -> Self {
+        Oisann::with_format(t, PrintSink, TypeNameFormat)
+    }
+}
+
+impl<T, C> Oisann<T, PrintSink, FnFormat<C>>
+where
+    C: Fn(&T, &mut fmt::Formatter<'_>) -> fmt::Result,
+{
+    /// Prints the value with a closure.
+    pub fn with_fn(t: T, format: C) -> Self {
+        Oisann::with_format(t, PrintSink, FnFormat(format))
+    }
+}
+
+impl<T, S: DropSink, F: Format<T>> Oisann<T, S, F> {
+    /// Announces the drop to `sink`, formatting the value with `format`.
+    pub fn with_format(t: T, sink: S, format: F) -> Self {
+        Oisann {
+            value: t,
+            sink,
+            format,
+            announce: announce::<T, S, F>,
+        }
+    }
+}
+
+// No bounds, so none for the struct either. Without #[may_dangle] the drop
+// checker still assumes this uses T, whatever `announce` ends up doing with it.
+impl<T, S, F> Drop for Oisann<T, S, F> {
     fn drop(&mut self) {
-        println!("{:?}", self.0);
+        // SAFETY: self is live and we have it exclusively. `announce` was built by
+        // a constructor for these exact T, S and F, bar lifetimes, which can only
+        // have shrunk since and don't change what the code does.
+        unsafe { (self.announce)(NonNull::from(self).cast()) }
+    }
+}
+
+/// Panics when dropped, to see how the code dropping it copes.
+///
+/// Doesn't panic if the thread is already panicking, that would abort instead.
+pub struct PanicOnDrop<T> {
+    value: T,
+}
+
+impl<T> PanicOnDrop<T> {
+    pub fn ne(t: T) -> Self {
+        PanicOnDrop { value: t }
+    }
+
+    /// Takes the value back out without panicking.
+    pub fn defuse(self) -> T {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: this is never dropped, so the value is read out exactly once.
+        unsafe { ptr::read(&this.value) }
+    }
+}
+
+impl<T> Drop for PanicOnDrop<T> {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            panic!("PanicOnDrop dropped");
+        }
     }
 }
 
 // If we use T here it will assume it drops the T here
 // which it does not. So using fn() -> T keeps it covariant
-// and also does not check for drop of T
-pub struct Empty<T>(PhantomData<fn() -> T>);
+// and also does not check for drop of T, PhantomCovariant
+// wraps exactly that.
+pub struct Empty<T>(PhantomCovariant<T>);
+
+/// An iterator that yields no `T`s.
+pub const fn empty<T>() -> Empty<T> {
+    Empty(PhantomCovariant::new())
+}
 
 impl<T> Iterator for Empty<T> {
     type Item = T;
     fn next(&mut self) -> Option<Self::Item> {
         None
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(0))
+    }
+}
+
+impl<T> DoubleEndedIterator for Empty<T> {
+    fn next_back(&mut self) -> Option<T> {
+        None
+    }
+}
+
+impl<T> ExactSizeIterator for Empty<T> {}
+
+impl<T> FusedIterator for Empty<T> {}
+
+// Written out rather than derived, a derive would require the same trait from T
+// even though there is never a T to clone, print or default.
+impl<T> Default for Empty<T> {
+    fn default() -> Self {
+        empty()
+    }
+}
+
+impl<T> Clone for Empty<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Empty<T> {}
+
+impl<T> fmt::Debug for Empty<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Empty")
+    }
+}
+
+// The mirror image of Empty: PhantomContravariant<T> is PhantomData<fn(*const T)>,
+// which only ever takes a T, so Discard is contravariant in T, and it never holds
+// one, so dropck ignores T as well.
+pub struct Discard<T>(PhantomContravariant<T>);
+
+/// A sink that accepts `T`s and drops them right away.
+pub const fn discard<T>() -> Discard<T> {
+    Discard(PhantomContravariant::new())
+}
+
+impl<T> Discard<T> {
+    /// Takes `t` and drops it.
+    pub fn push(&mut self, t: T) {
+        drop(t);
+    }
+}
+
+impl<T> Extend<T> for Discard<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(drop);
+    }
+}
+
+impl<T> FromIterator<T> for Discard<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut d = discard();
+        d.extend(iter);
+        d
+    }
+}
+
+/// Accepts every byte, like `io::sink()`.
+impl<T> io::Write for Discard<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// No bounds on T, like for Empty.
+impl<T> Default for Discard<T> {
+    fn default() -> Self {
+        discard()
+    }
+}
+
+impl<T> Clone for Discard<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Discard<T> {}
+
+impl<T> fmt::Debug for Discard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Discard")
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Boks, Oisann};
+    use crate::allocator::{AllocError, Allocator, Global};
+    use crate::canary::Canary;
+    use crate::live::LiveSet;
+    use crate::{Boks, Discard, Empty, Oisann, POISON, PanicOnDrop, discard, empty};
+    use std::alloc::Layout;
+    use std::any::Any;
+    use std::cell::Cell;
+    use std::fmt::Debug;
+    use std::io;
+    use std::marker::PhantomPinned;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::pin::Pin;
+    use std::ptr::NonNull;
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
 
     #[test]
     fn it_works() {
@@ -89,48 +942,739 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "nightly")]
     fn try_mutable_param() {
         let mut y = 42;
         // This work:
         // let stdb = Box::new(&mut y);
         // println!("{}", y);
 
-        let b = Boks::ne(&mut y);
+        let _b = Boks::ne(&mut y);
         // As Drop for Boks (with generic param) is implemented, so compiler assumes it access the
         // inner value T as dropped so can't borrow immutably or mutably once done in Boks.
         println!("{}", y);
     }
 
+    #[test]
+    #[cfg(not(feature = "nightly"))]
+    fn try_mutable_param_without_may_dangle() {
+        let mut y = 42;
+        let b = Boks::ne(&mut y);
+        // Without the nightly feature Drop for Boks has no #[may_dangle], so the
+        // compiler has to assume it reads the &mut i32 and y stays borrowed until
+        // b is gone. Moving the println! above the drop does not compile here,
+        // see tests/ui/boks_mutable_param_stable.rs
+        drop(b);
+        println!("{}", y);
+    }
+
     #[test]
     fn drop_boks_with_oiasnn() {
         let mut z = 42;
-        // This does not compile
+        // This does not compile, see tests/ui/box_oisann_mutable_param.rs
         // let b = Box::new(Oisann::ne(&mut z));
         // println!("{:?}", z);
 
         // But our code does, hence need to add PhantomData to tell
         // we are not accessing but dropping the inner value.
-        let b = Boks::ne(Oisann::ne(&mut z));
+        let _b = Boks::ne(Oisann::ne(&mut z));
         // Now with phantomData this won't compile as we said we
         // will drop the value, so look into the inner type drop whether
         // it access and if yes, make it not compile.
+        // See tests/ui/boks_oisann_mutable_param.rs
         // println!("{:?}", z);
     }
 
     #[test]
-    fn boks_without_non_null_is_invariant() {
-        // Boks has *mut T which is invariant
+    fn boks_with_non_null_is_covariant() {
+        // Boks used to have *mut T, which is invariant, now it has NonNull<T>.
+        // The asserts only read the values so the test builds without warnings.
 
-        let mut s = String::from("hei");
+        let s = String::from("hei");
         let mut stdb1: Box<&str> = Box::new(&*s);
-        let mut stdb2: Box<&'static str> = Box::new("hello");
+        assert_eq!(*stdb1, "hei");
+        let stdb2: Box<&'static str> = Box::new("hello");
         stdb1 = stdb2;
+        assert_eq!(*stdb1, "hello");
 
-        let mut s = String::from("hei");
+        let s = String::from("hei");
         let mut stdb1: Boks<&str> = Boks::ne(&*s);
-        let mut stdb2: Boks<&'static str> = Boks::ne("hello");
-        // This does not work as *mut is invariant so we can fix it by using NonNull
-        // which is covariant
+        assert_eq!(*stdb1, "hei");
+        let stdb2: Boks<&'static str> = Boks::ne("hello");
+        // This works because NonNull is covariant, so a Boks<&'static str> can be
+        // used as a Boks<&str>. With *mut T, which is invariant, it did not.
         stdb1 = stdb2;
+        assert_eq!(*stdb1, "hello");
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn boks_coerces_to_slice() {
+        let b: Boks<[i32]> = Boks::ne([1, 2, 3, 4]);
+        assert_eq!(b.len(), 4);
+        assert_eq!(&*b, &[1, 2, 3, 4]);
+
+        let empty: Boks<[String]> = Boks::ne([]);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn boks_holds_str_from_box() {
+        let b: Boks<str> = Boks::from(Box::<str>::from("hei"));
+        assert_eq!(&*b, "hei");
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn boks_coerces_to_dyn_trait() {
+        trait Speak {
+            fn speak(&self) -> String;
+        }
+
+        struct Named(String);
+
+        impl Speak for Named {
+            fn speak(&self) -> String {
+                format!("I am {}", self.0)
+            }
+        }
+
+        let b: Boks<dyn Speak> = Boks::ne(Named(String::from("Boks")));
+        assert_eq!(b.speak(), "I am Boks");
+    }
+
+    #[test]
+    fn drop_unsized_slice_drops_every_element() {
+        let set = LiveSet::new();
+        let b: Boks<[_]> = Boks::from(Box::new([set.track(()), set.track(())]) as Box<[_]>);
+        drop(b);
+        assert_eq!(set.dropped(), 2);
+    }
+
+    /// Hands out memory from `Global` while keeping a tally of live allocations,
+    /// so tests can see whether a Boks gave its memory back. With a limit set it
+    /// refuses any allocation that would take the bytes in use over it.
+    #[derive(Default)]
+    struct Counting {
+        live: Cell<usize>,
+        bytes: Cell<usize>,
+        limit: Option<usize>,
+    }
+
+    impl Counting {
+        fn with_limit(limit: usize) -> Self {
+            Self {
+                limit: Some(limit),
+                ..Self::default()
+            }
+        }
+    }
+
+    unsafe impl Allocator for Counting {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            let bytes = self.bytes.get() + layout.size();
+            if self.limit.is_some_and(|limit| bytes > limit) {
+                return Err(AllocError);
+            }
+            let p = Global.allocate(layout)?;
+            self.live.set(self.live.get() + 1);
+            self.bytes.set(bytes);
+            Ok(p)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.live.set(self.live.get() - 1);
+            self.bytes.set(self.bytes.get() - layout.size());
+            // SAFETY: forwarded from our caller, ptr came from Global.allocate above
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+    }
+
+    #[test]
+    fn new_in_returns_memory_to_allocator() {
+        let counting = Counting::default();
+        let b = Boks::new_in(String::from("hei"), &counting);
+        assert_eq!(Boks::allocator(&b).live.get(), 1);
+        assert_eq!(&*b, "hei");
+        drop(b);
+        assert_eq!(counting.live.get(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn new_in_coerces_to_dyn_trait() {
+        let counting = Counting::default();
+        let b: Boks<dyn Debug, &Counting> = Boks::new_in([1u8; 64], &counting);
+        assert!(format!("{:?}", &*b).starts_with("[1, 1, "));
+        drop(b);
+        assert_eq!(counting.live.get(), 0);
+    }
+
+    #[test]
+    fn zero_sized_values_do_not_allocate() {
+        thread_local! {
+            static DROPS: Cell<u32> = const { Cell::new(0) };
+        }
+
+        struct Unit;
+
+        impl Drop for Unit {
+            fn drop(&mut self) {
+                DROPS.set(DROPS.get() + 1);
+            }
+        }
+
+        let counting = Counting::default();
+        let b = Boks::new_in(Unit, &counting);
+        assert_eq!(counting.live.get(), 0);
+        drop(b);
+        // The destructor of T still runs even though there was nothing to free.
+        assert_eq!(DROPS.get(), 1);
+
+        let empty: Boks<[String; 0], _> = Boks::new_in([], &counting);
+        assert_eq!(counting.live.get(), 0);
+        drop(empty);
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn zero_length_slice_does_not_allocate() {
+        let counting = Counting::default();
+        let empty: Boks<[String], _> = Boks::new_in([], &counting);
+        assert_eq!(counting.live.get(), 0);
+        assert!(empty.is_empty());
+        drop(empty);
+    }
+
+    #[test]
+    fn over_aligned_values_keep_their_alignment() {
+        #[repr(align(256))]
+        struct Aligned(u8);
+
+        let b = Boks::ne(Aligned(7));
+        assert_eq!((&*b as *const Aligned).addr() % 256, 0);
+        assert_eq!(b.0, 7);
+    }
+
+    #[test]
+    fn into_inner_moves_value_out_and_frees() {
+        let counting = Counting::default();
+        let b = Boks::new_in(String::from("hei"), &counting);
+        let s = Boks::into_inner(b);
+        assert_eq!(counting.live.get(), 0);
+        assert_eq!(s, "hei");
+    }
+
+    #[test]
+    fn raw_round_trip_keeps_value() {
+        let raw = Boks::into_raw(Boks::ne(String::from("hei")));
+        // SAFETY: raw came from into_raw just above
+        let b = unsafe { Boks::from_raw(raw) };
+        assert_eq!(&*b, "hei");
+
+        let b: Boks<dyn Debug> = Boks::from(Box::new(vec![1, 2, 3]) as Box<dyn Debug>);
+        let p = Boks::into_non_null(b);
+        // SAFETY: p came from into_non_null just above, with its vtable intact
+        let b = unsafe { Boks::from_non_null(p) };
+        assert_eq!(format!("{:?}", &*b), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn raw_round_trip_with_allocator() {
+        let counting = Counting::default();
+        let (p, alloc) = Boks::into_non_null_with_allocator(Boks::new_in(7u64, &counting));
+        assert_eq!(counting.live.get(), 1);
+        // SAFETY: p and alloc came from into_non_null_with_allocator just above
+        let b = unsafe { Boks::from_non_null_in(p, alloc) };
+        assert_eq!(*b, 7);
+        drop(b);
+        assert_eq!(counting.live.get(), 0);
+    }
+
+    #[test]
+    fn leak_gives_static_reference() {
+        let r: &'static mut Vec<i32> = Boks::leak(Boks::ne(vec![1]));
+        r.push(2);
+        assert_eq!(r, &[1, 2]);
+        // SAFETY: r came from a leaked Global Boks and is not used again
+        drop(unsafe { Boks::from_raw(r) });
+    }
+
+    #[test]
+    fn new_uninit_is_written_in_place() {
+        let mut b = Boks::<[u64; 1024]>::new_uninit();
+        let p = b.as_mut_ptr();
+        for i in 0..1024 {
+            // SAFETY: p points to room for 1024 u64s, i is in bounds
+            unsafe { p.cast::<u64>().add(i).write(i as u64) };
+        }
+        // SAFETY: every element was written above
+        let b = unsafe { b.assume_init() };
+        assert_eq!(b[1023], 1023);
+
+        let b = Boks::write(Boks::new_uninit(), String::from("hei"));
+        assert_eq!(&*b, "hei");
+    }
+
+    #[test]
+    fn new_zeroed_is_all_zero() {
+        // SAFETY: all zero is a valid [u32; 16]
+        let b = unsafe { Boks::<[u32; 16]>::new_zeroed().assume_init() };
+        assert_eq!(*b, [0; 16]);
+
+        // SAFETY: all zero is a valid u8
+        let b = unsafe { Boks::<u8>::new_zeroed_slice(32).assume_init() };
+        assert!(b.iter().all(|&x| x == 0));
+        assert_eq!(b.len(), 32);
+    }
+
+    #[test]
+    fn new_uninit_slice_is_initialised_per_element() {
+        let counting = Counting::default();
+        let mut b = Boks::<String, _>::new_uninit_slice_in(3, &counting);
+        assert_eq!(counting.live.get(), 1);
+        for (i, slot) in b.iter_mut().enumerate() {
+            slot.write(i.to_string());
+        }
+        // SAFETY: every element was written above
+        let b = unsafe { b.assume_init() };
+        assert_eq!(&*b, ["0", "1", "2"]);
+        drop(b);
+        assert_eq!(counting.live.get(), 0);
+
+        let empty = Boks::<String, _>::new_uninit_slice_in(0, &counting);
+        assert_eq!(counting.live.get(), 0);
+        drop(empty);
+    }
+
+    #[test]
+    fn try_new_in_reports_failure_over_limit() {
+        let set = LiveSet::new();
+        let limited = Counting::with_limit(64);
+        let first = Boks::try_new_in(set.track([0u8; 32]), &limited).unwrap();
+        assert_eq!(limited.live.get(), 1);
+
+        // The second one would take us over the limit, so the value comes straight
+        // back out as an error and is dropped instead of being leaked.
+        assert!(Boks::try_new_in(set.track([0u8; 32]), &limited).is_err());
+        assert_eq!(set.dropped(), 1);
+        assert_eq!(limited.live.get(), 1);
+
+        assert!(Boks::<[u8; 128], _>::try_new_uninit_in(&limited).is_err());
+        assert_eq!(limited.live.get(), 1);
+
+        drop(first);
+        set.assert_clean();
+        assert_eq!(limited.live.get(), 0);
+        assert_eq!(limited.bytes.get(), 0);
+
+        // Zero-sized values never ask the allocator, so they can't fail.
+        let zero = Counting::with_limit(0);
+        assert!(Boks::try_new_in((), &zero).is_ok());
+    }
+
+    #[test]
+    fn try_new_succeeds_on_global() {
+        let b = Boks::try_new(String::from("hei")).unwrap();
+        assert_eq!(&*b, "hei");
+
+        let b = Boks::write(Boks::try_new_uninit().unwrap(), 42);
+        assert_eq!(*b, 42);
+    }
+
+    /// Returns `Pending` the first time it is polled and `Ready` after that.
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn pinned_future_can_be_polled() {
+        let mut cx = Context::from_waker(Waker::noop());
+
+        // The async block keeps a reference to its own local across the await,
+        // so it is self-referential and !Unpin once it has been polled.
+        let mut fut = Boks::pin(async {
+            let s = String::from("hei");
+            let r = &s;
+            YieldOnce(false).await;
+            r.len()
+        });
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(3));
+
+        let b: Boks<dyn Future<Output = ()>> =
+            Boks::from(Box::new(YieldOnce(true)) as Box<dyn Future<Output = ()>>);
+        let mut fut = Pin::from(b);
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(()));
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn pinned_dyn_future_can_be_polled() {
+        let mut cx = Context::from_waker(Waker::noop());
+
+        // Coerced while pinned, through PinCoerceUnsized.
+        let mut fut: Pin<Boks<dyn Future<Output = usize>>> = Boks::pin(async {
+            let s = String::from("hei");
+            let r = &s;
+            YieldOnce(false).await;
+            r.len()
+        });
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(3));
+
+        let b: Boks<dyn Future<Output = ()>> = Boks::ne(YieldOnce(true));
+        let mut fut = Pin::from(b);
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(()));
+    }
+
+    #[test]
+    fn boks_is_unpin_even_if_value_is_not() {
+        fn assert_unpin<T: Unpin>(_: &T) {}
+
+        let b = Boks::ne(PhantomPinned);
+        assert_unpin(&b);
+        // Moving the pinned Boks around is fine, the value stays where it is.
+        let pinned = Boks::into_pin(b);
+        let addr = (&*pinned as *const PhantomPinned).addr();
+        let moved = pinned;
+        assert_eq!((&*moved as *const PhantomPinned).addr(), addr);
+    }
+
+    #[test]
+    fn compares_and_orders_like_the_value() {
+        assert_eq!(Boks::ne(1), Boks::ne(1));
+        assert_ne!(Boks::ne(1), Boks::ne(2));
+        assert!(Boks::ne(1) < Boks::ne(2));
+        assert!(Boks::ne(f64::NAN) != Boks::ne(f64::NAN));
+        assert_eq!(Boks::ne("a").cmp(&Boks::ne("b")), std::cmp::Ordering::Less);
+
+        let a: Boks<[i32]> = Boks::from(Box::<[i32]>::from([1, 2]));
+        let b: Boks<[i32]> = Boks::from(Box::<[i32]>::from([1, 2]));
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn usable_as_map_key() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let mut hash = HashMap::new();
+        hash.insert(Boks::ne(String::from("hei")), 1);
+        assert_eq!(hash.get(&String::from("hei")), Some(&1));
+
+        let mut btree = BTreeMap::new();
+        btree.insert(Boks::ne(2), "two");
+        btree.insert(Boks::ne(1), "one");
+        assert_eq!(btree.get(&1), Some(&"one"));
+        assert_eq!(btree.keys().map(|k| **k).collect::<Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn formats_like_the_value() {
+        let b = Boks::ne(String::from("hei"));
+        assert_eq!(format!("{b}"), "hei");
+        assert_eq!(format!("{b:?}"), "\"hei\"");
+        assert_eq!(format!("{b:p}"), format!("{:p}", &*b));
+    }
+
+    #[test]
+    fn clone_from_reuses_allocation() {
+        let counting = Counting::default();
+        let source = Boks::new_in(String::from("hei"), &counting);
+        let mut b = source.clone();
+        assert_eq!(b, source);
+        assert_eq!(counting.live.get(), 2);
+
+        let addr = (&*b as *const String).addr();
+        b.clone_from(&Boks::new_in(String::from("hallo"), &counting));
+        assert_eq!(&**b, "hallo");
+        assert_eq!((&*b as *const String).addr(), addr);
+    }
+
+    /// A `Boks<[T]>` in `alloc`, without the nightly coercion from `[T; N]`.
+    fn slice_in<T, A: Allocator, const N: usize>(values: [T; N], alloc: A) -> Boks<[T], A> {
+        let mut b = Boks::new_uninit_slice_in(N, alloc);
+        for (slot, t) in b.iter_mut().zip(values) {
+            slot.write(t);
+        }
+        // SAFETY: all N elements were written above.
+        unsafe { b.assume_init() }
+    }
+
+    #[test]
+    fn clones_unsized_slices_and_strs() {
+        let counting = Counting::default();
+        let mut b = slice_in([String::from("a"), String::from("b")], &counting);
+        let c = b.clone();
+        assert_eq!(c, b);
+        assert_eq!(counting.live.get(), 2);
+
+        let addr = b.as_ptr().addr();
+        b.clone_from(&c);
+        assert_eq!(b.as_ptr().addr(), addr);
+
+        let s: Boks<str> = Boks::from(Box::<str>::from("hei"));
+        assert_eq!(&*s.clone(), "hei");
+
+        assert!(Boks::<[String]>::default().is_empty());
+        assert_eq!(&*Boks::<str>::default(), "");
+    }
+
+    #[test]
+    fn panicking_slice_clone_drops_what_it_cloned() {
+        struct PanicOnThird<'a>(&'a Cell<u32>, u32);
+
+        impl Clone for PanicOnThird<'_> {
+            fn clone(&self) -> Self {
+                assert!(self.1 != 3, "cloned the third");
+                PanicOnThird(self.0, self.1)
+            }
+        }
+
+        impl Drop for PanicOnThird<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let counting = Counting::default();
+        let b = slice_in([1, 2, 3, 4].map(|i| PanicOnThird(&drops, i)), &counting);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| b.clone()));
+        assert!(result.is_err());
+        // The two clones made before the panic are dropped, and their memory freed.
+        assert_eq!(drops.get(), 2);
+        assert_eq!(counting.live.get(), 1);
+    }
+
+    #[test]
+    fn converts_and_borrows() {
+        use std::borrow::{Borrow, BorrowMut};
+
+        let mut b: Boks<Vec<i32>> = Boks::from(vec![1]);
+        b.as_mut().push(2);
+        BorrowMut::<Vec<i32>>::borrow_mut(&mut b).push(3);
+        assert_eq!(b.as_ref(), &[1, 2, 3]);
+        assert_eq!(Borrow::<Vec<i32>>::borrow(&b).len(), 3);
+        assert_eq!(*Boks::<Vec<i32>>::default(), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn move_boks_into_scoped_thread() {
+        let mut y = 42;
+        thread::scope(|s| {
+            let mut b = Boks::ne(&mut y);
+            s.spawn(move || **b += 1);
+        });
+        assert_eq!(y, 43);
+    }
+
+    #[test]
+    fn share_boks_between_scoped_threads() {
+        let b = Boks::ne(vec![1, 2, 3]);
+        let sums: Vec<i32> = thread::scope(|s| {
+            let handles: Vec<_> = (0..4).map(|_| s.spawn(|| b.iter().sum())).collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(sums, [6; 4]);
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn boks_returned_from_scoped_thread_may_dangle() {
+        let mut y = 42;
+        let _b = thread::scope(|s| {
+            s.spawn(|| {
+                let mut b = Boks::ne(&mut y);
+                **b += 1;
+                b
+            })
+            .join()
+            .unwrap()
+        });
+        // _b still holds &mut y, but as with try_mutable_param the Boks is only
+        // dropped at the end of the scope and #[may_dangle] lets y be used before it.
+        // On stable this does not compile, see
+        // tests/ui/boks_scoped_thread_mutable_param_stable.rs
+        assert_eq!(y, 43);
+        // Using _b itself after this would not compile, y is borrowed mutably by it.
+        // println!("{}", _b);
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn may_dangle_only_covers_value_not_allocator() {
+        let counting = Counting::default();
+        let mut y = 42;
+        let _b = Boks::new_in(&mut y, &counting);
+        // T is behind #[may_dangle], so y can still be used while the Boks lives.
+        // On stable it can't, see tests/ui/boks_new_in_mutable_param_stable.rs
+        assert_eq!(y, 42);
+
+        // But the allocator is not: dropping counting here would not compile, the
+        // Boks still needs it to free its memory. See
+        // tests/ui/boks_allocator_dropped_first.rs
+    }
+
+    #[test]
+    fn panicking_destructor_still_frees() {
+        let counting = Counting::default();
+        let set = LiveSet::new();
+        let b = Boks::new_in(PanicOnDrop::ne(set.track(1)), &counting);
+        assert_eq!(counting.live.get(), 1);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| drop(b)));
+        assert!(result.is_err());
+        // The fields of PanicOnDrop are still dropped while unwinding, once.
+        assert_eq!(set.dropped(), 1);
+        assert_eq!(counting.live.get(), 0);
+        assert_eq!(counting.bytes.get(), 0);
+    }
+
+    #[test]
+    fn panicking_element_still_drops_the_rest() {
+        let counting = Counting::default();
+        let set = LiveSet::new();
+        let mut b = Boks::new_uninit_slice_in(3, &counting);
+        for (i, slot) in b.iter_mut().enumerate() {
+            slot.write(PanicOnDrop::ne(set.track(i)));
+        }
+        // SAFETY: every element was written above.
+        let b = unsafe { b.assume_init() };
+
+        // The first element panics, the other two are dropped while unwinding
+        // without panicking again.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| drop(b)));
+        assert!(result.is_err());
+        set.assert_clean();
+        assert_eq!(set.dropped(), 3);
+        assert_eq!(counting.live.get(), 0);
+    }
+
+    #[test]
+    fn panicking_destructor_in_dyn_boks() {
+        let set = LiveSet::new();
+        let b: Boks<dyn Any> = Boks::from(Box::new(PanicOnDrop::ne(set.track(1))) as Box<dyn Any>);
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| drop(b))).is_err());
+        assert_eq!(set.dropped(), 1);
+    }
+
+    #[test]
+    fn defused_does_not_panic() {
+        let b = Boks::ne(PanicOnDrop::ne(5));
+        assert_eq!(Boks::into_inner(b).defuse(), 5);
+    }
+
+    #[test]
+    fn unwind_safe_boks_crosses_catch_unwind() {
+        // No AssertUnwindSafe needed, Cell<u8> is UnwindSafe and so is the Boks.
+        let b = Boks::ne(Cell::new(1u8));
+        let result = panic::catch_unwind(move || {
+            b.set(2);
+            b.get()
+        });
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    /// Checks that every block handed back to it was filled with POISON first.
+    #[derive(Default)]
+    struct Inspecting {
+        freed: Cell<usize>,
+        poisoned: Cell<usize>,
+    }
+
+    unsafe impl Allocator for Inspecting {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            // SAFETY: ptr is valid for layout.size() bytes until it is freed below.
+            let bytes = unsafe { std::slice::from_raw_parts(ptr.as_ptr(), layout.size()) };
+            self.freed.set(self.freed.get() + 1);
+            if bytes.iter().all(|&b| b == POISON) {
+                self.poisoned.set(self.poisoned.get() + 1);
+            }
+            // SAFETY: forwarded from our caller, ptr came from Global.allocate above
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn freed_memory_is_poisoned_in_debug_builds() {
+        let inspecting = Inspecting::default();
+        drop(Boks::new_in(Canary::ne(), &inspecting));
+        drop(Boks::new_in([7u8; 3], &inspecting));
+        let c = Boks::into_inner(Boks::new_in(Canary::ne(), &inspecting));
+        assert!(c.is_alive());
+        // SAFETY: zeroes are valid u32s.
+        let b = unsafe { Boks::<u32, _>::new_zeroed_slice_in(5, &inspecting).assume_init() };
+        assert_eq!(*b, [0; 5]);
+        drop(b);
+        assert_eq!(inspecting.freed.get(), 4);
+        assert_eq!(inspecting.poisoned.get(), 4);
+    }
+
+    /// Neither Copy, Clone, Default nor Debug.
+    struct NonCopy;
+
+    #[test]
+    fn empty_yields_nothing_from_either_end() {
+        let mut e = empty::<NonCopy>();
+        assert_eq!(e.len(), 0);
+        assert_eq!(e.size_hint(), (0, Some(0)));
+        assert!(e.next().is_none());
+        assert!(e.next_back().is_none());
+        assert!(e.next().is_none());
+        assert_eq!(
+            empty::<u8>().rev().chain([1, 2]).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(Empty::<String>::default().count(), 0);
+    }
+
+    #[test]
+    fn empty_is_copy_without_t_being_copy() {
+        fn assert_copy<T: Copy>(_: T) {}
+
+        let e = empty::<NonCopy>();
+        let copy = e;
+        assert_copy(e);
+        assert_eq!(copy.clone().len(), 0);
+        assert_eq!(format!("{e:?}"), "Empty");
+    }
+
+    #[test]
+    fn discard_drops_everything_it_gets() {
+        let set = LiveSet::new();
+        let mut d = discard();
+        d.push(set.track(0));
+        d.extend((1..3).map(|i| set.track(i)));
+        let _: Discard<_> = (3..5).map(|i| set.track(i)).collect();
+        assert_eq!(set.created(), 5);
+        set.assert_clean();
+    }
+
+    #[test]
+    fn discard_as_writer() {
+        use std::io::Write;
+
+        let mut d = discard::<NonCopy>();
+        assert_eq!(d.write(b"hei").unwrap(), 3);
+        writeln!(d, "{}", 42).unwrap();
+        io::copy(&mut &b"abc"[..], &mut d).unwrap();
+        let copy = d;
+        assert_eq!(format!("{copy:?} {d:?}"), "Discard Discard");
     }
 }
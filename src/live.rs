@@ -0,0 +1,308 @@
+//! Catching leaks and double drops by counting what is still alive.
+
+use std::any;
+use std::fmt::{self, Write};
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+#[derive(Default)]
+struct State {
+    /// Indexed by the id of the tracked value.
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    label: String,
+    drops: usize,
+}
+
+/// Counts the values wrapped with [`LiveSet::track`] as they are created and
+/// dropped.
+///
+/// When the set itself is dropped, at the end of the test, it panics with a
+/// report if any of them is still alive (leaked with `mem::forget`, an `Rc`
+/// cycle, a missing `drop_in_place`...) or was dropped more than once.
+#[derive(Default)]
+pub struct LiveSet {
+    state: Arc<Mutex<State>>,
+}
+
+impl LiveSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wraps `value`, labelled with the name of its type.
+    pub fn track<T>(&self, value: T) -> Tracked<T> {
+        self.track_as(any::type_name::<T>(), value)
+    }
+
+    /// Wraps `value`, labelled with `label` in the report.
+    pub fn track_as<T>(&self, label: impl Into<String>, value: T) -> Tracked<T> {
+        Tracked::new(&self.state, label.into(), value)
+    }
+
+    /// How many values were tracked so far.
+    pub fn created(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// How many drops happened so far, double drops counted twice.
+    pub fn dropped(&self) -> usize {
+        self.lock().entries.iter().map(|e| e.drops).sum()
+    }
+
+    /// How many tracked values have not been dropped yet.
+    pub fn live(&self) -> usize {
+        self.lock().entries.iter().filter(|e| e.drops == 0).count()
+    }
+
+    /// Describes every value that is still alive or was dropped more than once,
+    /// `None` if there are none.
+    pub fn report(&self) -> Option<String> {
+        let state = self.lock();
+        let leaked: Vec<_> = state
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.drops == 0)
+            .collect();
+        let doubled: Vec<_> = state
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.drops > 1)
+            .collect();
+        if leaked.is_empty() && doubled.is_empty() {
+            return None;
+        }
+
+        let mut report = format!(
+            "{} of {} tracked values leaked, {} dropped more than once",
+            leaked.len(),
+            state.entries.len(),
+            doubled.len()
+        );
+        for (id, e) in leaked {
+            let _ = write!(report, "\n  leaked: #{id} {}", e.label);
+        }
+        for (id, e) in doubled {
+            let _ = write!(report, "\n  dropped {} times: #{id} {}", e.drops, e.label);
+        }
+        Some(report)
+    }
+
+    /// Panics with the [report](Self::report) if anything leaked or was dropped
+    /// more than once.
+    #[track_caller]
+    pub fn assert_clean(&self) {
+        if let Some(report) = self.report() {
+            panic!("{report}");
+        }
+    }
+
+    /// A value that panicked in its destructor could poison the lock, the counts
+    /// are still right.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl fmt::Debug for LiveSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LiveSet")
+            .field("created", &self.created())
+            .field("live", &self.live())
+            .finish()
+    }
+}
+
+impl Drop for LiveSet {
+    fn drop(&mut self) {
+        // A second panic while unwinding would abort, and hide the first one.
+        if !thread::panicking() {
+            self.assert_clean();
+        }
+    }
+}
+
+/// A value tracked by a [`LiveSet`], reachable through `Deref`.
+pub struct Tracked<T> {
+    value: T,
+    id: usize,
+    // Only released on the first drop, so dropping twice is reported instead of
+    // freeing the state under the set's feet.
+    state: ManuallyDrop<Arc<Mutex<State>>>,
+}
+
+impl<T> Tracked<T> {
+    fn new(state: &Arc<Mutex<State>>, label: String, value: T) -> Self {
+        let mut entries = lock(state);
+        let id = entries.entries.len();
+        entries.entries.push(Entry { label, drops: 0 });
+        Tracked {
+            value,
+            id,
+            state: ManuallyDrop::new(Arc::clone(state)),
+        }
+    }
+}
+
+/// The clone is tracked as a value of its own, under the same label.
+impl<T: Clone> Clone for Tracked<T> {
+    fn clone(&self) -> Self {
+        let label = lock(&self.state).entries[self.id].label.clone();
+        Tracked::new(&self.state, label, self.value.clone())
+    }
+}
+
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Tracked<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Tracked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Tracked")
+            .field(&self.id)
+            .field(&self.value)
+            .finish()
+    }
+}
+
+impl<T> Drop for Tracked<T> {
+    fn drop(&mut self) {
+        let first = {
+            let mut state = lock(&self.state);
+            let entry = &mut state.entries[self.id];
+            entry.drops += 1;
+            entry.drops == 1
+        };
+        if first {
+            // SAFETY: first drop, so nobody took it before.
+            unsafe { ManuallyDrop::drop(&mut self.state) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Boks;
+    use std::cell::RefCell;
+    use std::mem;
+    use std::ptr;
+    use std::rc::Rc;
+
+    #[test]
+    fn counts_creations_and_drops() {
+        let set = LiveSet::new();
+        let a = set.track(1);
+        let b = set.track_as("b", String::from("hei"));
+        assert_eq!((set.created(), set.live(), set.dropped()), (2, 2, 0));
+        assert_eq!(*a + 1, 2);
+        assert_eq!(b.len(), 3);
+        drop(a);
+        assert_eq!((set.live(), set.dropped()), (1, 1));
+        assert!(set.report().unwrap().contains("leaked: #1 b"));
+        drop(b);
+        assert_eq!(set.report(), None);
+    }
+
+    #[test]
+    fn boks_drops_its_value_exactly_once() {
+        let set = LiveSet::new();
+        let b = Boks::ne(set.track(42));
+        assert_eq!(set.live(), 1);
+        drop(b);
+        assert_eq!(set.dropped(), 1);
+
+        let b = Boks::ne(set.track(1));
+        let inner = Boks::into_inner(b);
+        assert_eq!(set.live(), 1);
+        drop(inner);
+
+        let mut b = Boks::ne(set.track(1));
+        // Replaces the 1 with a clone of the 2, then the Boks holding 2 goes away.
+        b.clone_from(&Boks::ne(set.track(2)));
+        assert_eq!(**b, 2);
+        assert_eq!((set.created(), set.live()), (5, 1));
+        *b = set.track(3);
+        drop(b);
+        set.assert_clean();
+        assert_eq!(set.dropped(), 6);
+    }
+
+    #[test]
+    fn boks_slice_drops_every_element_once() {
+        let set = LiveSet::new();
+        let mut b = Boks::<Tracked<usize>>::new_uninit_slice(4);
+        for (i, slot) in b.iter_mut().enumerate() {
+            slot.write(set.track(i));
+        }
+        // SAFETY: every element was written above.
+        let b = unsafe { b.assume_init() };
+        assert_eq!(set.live(), 4);
+        drop(b);
+        assert_eq!(set.dropped(), 4);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "1 of 2 tracked values leaked, 0 dropped more than once\n  leaked: #0 forgotten"
+    )]
+    fn forget_is_a_leak() {
+        let set = LiveSet::new();
+        mem::forget(set.track_as("forgotten", 1));
+        drop(set.track(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "leaked: #0 node")]
+    fn rc_cycle_is_a_leak() {
+        struct Node(RefCell<Option<Rc<Tracked<Node>>>>);
+
+        let set = LiveSet::new();
+        let a = Rc::new(set.track_as("node", Node(RefCell::new(None))));
+        *a.0.borrow_mut() = Some(Rc::clone(&a));
+    }
+
+    #[test]
+    #[should_panic(expected = "dropped 2 times: #0 twice")]
+    fn double_drop_is_reported() {
+        let set = LiveSet::new();
+        let mut t = ManuallyDrop::new(set.track_as("twice", 1));
+        // SAFETY: dropping a Tracked<i32> twice is what it is there to catch, the
+        // i32 doesn't mind.
+        unsafe {
+            ptr::drop_in_place(&mut *t);
+            ptr::drop_in_place(&mut *t);
+        }
+    }
+
+    #[test]
+    fn no_second_panic_while_unwinding() {
+        let result = std::panic::catch_unwind(|| {
+            let set = LiveSet::new();
+            mem::forget(set.track(1));
+            panic!("the real failure");
+        });
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"the real failure"));
+    }
+}
@@ -0,0 +1,209 @@
+//! Named zero-sized markers for the usual `PhantomData` incantations.
+//!
+//! A `PhantomData<X>` field makes a type behave as if it held an `X`, for variance,
+//! for the drop checker and for auto traits alike. Picking the right `X` for the
+//! effect you want is easy to get wrong, so each marker here spells out one
+//! choice and what it does to the three of them.
+//!
+//! | marker                    | variance in `T` | dropck owns `T` | auto traits         |
+//! |---------------------------|-----------------|-----------------|---------------------|
+//! | `PhantomCovariant<T>`     | covariant       | no              | all                 |
+//! | `PhantomContravariant<T>` | contravariant   | no              | all                 |
+//! | `PhantomInvariant<T>`     | invariant       | no              | all                 |
+//! | `PhantomOwns<T>`          | covariant       | yes             | same as `T`         |
+//! | `PhantomBorrows<'a, T>`   | covariant       | no              | same as `&'a T`     |
+//! | `PhantomNotSend`          | -               | -               | all but `Send`      |
+//!
+//! None of the markers put any bounds on `T` for `Copy`, `Clone`, `Default`,
+//! `Debug`, comparisons or `Hash`, unlike a derive on a struct holding them would.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+/// Implements the usual traits for a marker without placing bounds on `T`.
+macro_rules! marker_impls {
+    ($name:ident<$($lt:lifetime,)? $t:ident>) => {
+        impl<$($lt,)? $t: ?Sized> $name<$($lt,)? $t> {
+            pub const fn new() -> Self {
+                Self(PhantomData)
+            }
+        }
+
+        impl<$($lt,)? $t: ?Sized> Clone for $name<$($lt,)? $t> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<$($lt,)? $t: ?Sized> Copy for $name<$($lt,)? $t> {}
+
+        impl<$($lt,)? $t: ?Sized> Default for $name<$($lt,)? $t> {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl<$($lt,)? $t: ?Sized> fmt::Debug for $name<$($lt,)? $t> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}<{}>", stringify!($name), std::any::type_name::<$t>())
+            }
+        }
+
+        impl<$($lt,)? $t: ?Sized> PartialEq for $name<$($lt,)? $t> {
+            fn eq(&self, _: &Self) -> bool {
+                true
+            }
+        }
+
+        impl<$($lt,)? $t: ?Sized> Eq for $name<$($lt,)? $t> {}
+
+        impl<$($lt,)? $t: ?Sized> PartialOrd for $name<$($lt,)? $t> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl<$($lt,)? $t: ?Sized> Ord for $name<$($lt,)? $t> {
+            fn cmp(&self, _: &Self) -> Ordering {
+                Ordering::Equal
+            }
+        }
+
+        impl<$($lt,)? $t: ?Sized> Hash for $name<$($lt,)? $t> {
+            fn hash<H: Hasher>(&self, _: &mut H) {}
+        }
+    };
+}
+
+/// Acts as if the type can produce a `T`, without ever holding one.
+///
+/// - Variance: covariant in `T`, a `PhantomCovariant<&'static str>` can be used as
+///   a `PhantomCovariant<&'a str>`.
+/// - Dropck: does not own a `T`, so a `Drop` impl next to it is not assumed to
+///   drop one.
+/// - Auto traits: always `Send`, `Sync`, `Unpin`, `UnwindSafe` and `RefUnwindSafe`,
+///   whatever `T` is.
+///
+/// Built on the `PhantomData<fn() -> T>` trick `Empty` uses, returning a pointer
+/// instead so `T` may be unsized.
+pub struct PhantomCovariant<T: ?Sized>(PhantomData<fn() -> *const T>);
+
+/// Acts as if the type can consume a `T`, without ever holding one.
+///
+/// - Variance: contravariant in `T`, a `PhantomContravariant<&'a str>` can be used
+///   as a `PhantomContravariant<&'static str>`.
+/// - Dropck: does not own a `T`.
+/// - Auto traits: always `Send`, `Sync`, `Unpin`, `UnwindSafe` and `RefUnwindSafe`.
+///
+/// Built on `PhantomData<fn(T)>`, taking a pointer so `T` may be unsized.
+pub struct PhantomContravariant<T: ?Sized>(PhantomData<fn(*const T)>);
+
+/// Acts as if the type both produces and consumes a `T`, so `T` is fixed exactly.
+///
+/// - Variance: invariant in `T`, needed whenever a `T` can be written through a
+///   shared path, like the `T` in `Cell<T>` or `&mut T`.
+/// - Dropck: does not own a `T`.
+/// - Auto traits: always `Send`, `Sync`, `Unpin`, `UnwindSafe` and `RefUnwindSafe`.
+///
+/// Built on `PhantomData<fn(T) -> T>`, with pointers so `T` may be unsized.
+pub struct PhantomInvariant<T: ?Sized>(PhantomData<fn(*const T) -> *const T>);
+
+/// Acts as if the type owns a `T` and drops it.
+///
+/// - Variance: covariant in `T`.
+/// - Dropck: owns a `T`. Combined with `#[may_dangle]` on a `Drop` impl this tells
+///   the drop checker the destructor will still drop a `T`, so whatever `T`'s own
+///   destructor uses must be alive, even though the impl itself won't touch `T`.
+/// - Auto traits: exactly those of `T`.
+///
+/// This is plain `PhantomData<T>`, what `Boks` uses next to its `NonNull<T>`. It is
+/// an alias rather than a wrapper because `CoerceUnsized` only lets `PhantomData`
+/// fields tag along next to the pointer being coerced.
+pub type PhantomOwns<T> = PhantomData<T>;
+
+/// Acts as if the type holds a `&'a T`.
+///
+/// - Variance: covariant in `'a` and `T`.
+/// - Dropck: does not own a `T`, but ties the type to `'a`, so it can't outlive
+///   the borrow.
+/// - Auto traits: those of `&'a T`, so it is only `Send` if `T` is `Sync`.
+///
+/// Built on `PhantomData<&'a T>`.
+pub struct PhantomBorrows<'a, T: ?Sized>(PhantomData<&'a T>);
+
+/// Makes the type `!Send` while leaving it `Sync`, like a `MutexGuard`.
+///
+/// Useful for values that must be dropped on the thread that created them.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PhantomNotSend(PhantomData<*const ()>);
+
+impl PhantomNotSend {
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+// SAFETY: there is nothing in here to share, the raw pointer is only a marker to
+// opt out of Send.
+unsafe impl Sync for PhantomNotSend {}
+
+marker_impls!(PhantomCovariant<T>);
+marker_impls!(PhantomContravariant<T>);
+marker_impls!(PhantomInvariant<T>);
+marker_impls!(PhantomBorrows<'a, T>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assert_contravariant, assert_covariant, assert_impl, assert_invariant, assert_not_impl,
+    };
+    use std::cell::Cell;
+    use std::marker::PhantomPinned;
+    use std::panic::{RefUnwindSafe, UnwindSafe};
+    use std::rc::Rc;
+
+    assert_covariant!(for<'a> PhantomCovariant<&'a i32>);
+    assert_contravariant!(for<'a> PhantomContravariant<&'a i32>);
+    assert_invariant!(for<'a> PhantomInvariant<&'a i32>);
+    assert_covariant!(for<'a> PhantomOwns<&'a i32>);
+    assert_covariant!(for<'a> PhantomBorrows<'a, i32>);
+    assert_covariant!(for<'a> PhantomBorrows<'static, &'a i32>);
+
+    assert_impl!(PhantomCovariant<Rc<u8>>: Send, Sync, Unpin, UnwindSafe, RefUnwindSafe);
+    assert_impl!(PhantomContravariant<Rc<u8>>: Send, Sync, Unpin, UnwindSafe, RefUnwindSafe);
+    assert_impl!(PhantomInvariant<Rc<u8>>: Send, Sync, Unpin, UnwindSafe, RefUnwindSafe);
+    assert_impl!(PhantomCovariant<PhantomPinned>: Unpin);
+    assert_impl!(PhantomOwns<u8>: Send, Sync, Unpin, UnwindSafe, RefUnwindSafe);
+    assert_not_impl!(PhantomOwns<Rc<u8>>: Send, Sync);
+    assert_not_impl!(PhantomOwns<PhantomPinned>: Unpin);
+    assert_impl!(PhantomBorrows<'static, Cell<u8>>: Unpin);
+    assert_not_impl!(PhantomBorrows<'static, Cell<u8>>: Send, Sync);
+    assert_impl!(PhantomBorrows<'static, u8>: Send, Sync);
+    assert_impl!(PhantomNotSend: Sync, Unpin, UnwindSafe, RefUnwindSafe);
+    assert_not_impl!(PhantomNotSend: Send);
+
+    // Unlike #[derive], none of the traits need anything from T.
+    assert_impl!(PhantomOwns<String>: Copy, Default, fmt::Debug, Eq, Ord, Hash);
+    assert_impl!(PhantomInvariant<str>: Copy, Default, fmt::Debug, Eq, Ord, Hash);
+
+    #[test]
+    fn markers_are_zero_sized() {
+        assert_eq!(size_of::<PhantomCovariant<[u8; 64]>>(), 0);
+        assert_eq!(size_of::<PhantomContravariant<[u8; 64]>>(), 0);
+        assert_eq!(size_of::<PhantomInvariant<[u8; 64]>>(), 0);
+        assert_eq!(size_of::<PhantomOwns<[u8; 64]>>(), 0);
+        assert_eq!(size_of::<PhantomBorrows<'_, [u8; 64]>>(), 0);
+        assert_eq!(size_of::<PhantomNotSend>(), 0);
+    }
+
+    #[test]
+    fn debug_names_the_marker_and_type() {
+        assert_eq!(
+            format!("{:?}", PhantomInvariant::<u8>::new()),
+            "PhantomInvariant<u8>"
+        );
+    }
+}
@@ -0,0 +1,502 @@
+//! A single-threaded reference-counted pointer, the shared counterpart to `Boks`.
+
+use crate::Boks;
+use crate::marker::PhantomOwns;
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ops::Deref;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::process;
+use std::ptr::{self, NonNull};
+
+/// What an `Rk` points to, allocated as a `Boks`. The value is dropped separately
+/// once the last `Rk` is gone, so the `Boks` must never drop it.
+struct RkInner<T> {
+    strong: Cell<usize>,
+    /// One more than the number of `Weak`s while there is any `Rk`, for the one
+    /// `Weak` all of them share, so the memory is freed by whichever goes last.
+    weak: Cell<usize>,
+    value: ManuallyDrop<T>,
+}
+
+/// Gives up like `Rc` does instead of wrapping around, which would let the value
+/// be dropped while there are still pointers to it. Takes leaking `usize::MAX`
+/// clones with `mem::forget`, within reach on 32-bit targets.
+fn increment(count: &Cell<usize>) {
+    match count.get().checked_add(1) {
+        Some(n) => count.set(n),
+        None => process::abort(),
+    }
+}
+
+/// A pointer sharing ownership of a `T` with its clones, like `Rc`.
+///
+/// The `T` is dropped with the last `Rk`, the memory once no `Weak` is left either.
+pub struct Rk<T> {
+    p: NonNull<RkInner<T>>,
+    // Like Boks: together with #[may_dangle] on Drop this tells the drop checker
+    // that dropping an Rk may drop a T. Owning an RkInner<T> wouldn't do, its T is
+    // in a ManuallyDrop, which the drop checker knows never drops it.
+    phantom: PhantomOwns<T>,
+}
+
+/// A pointer to the value of an `Rk` that doesn't keep it alive, like `rc::Weak`.
+///
+/// Never drops the `T`, so unlike `Rk` it doesn't own one as far as the drop
+/// checker is concerned.
+pub struct Weak<T> {
+    p: NonNull<RkInner<T>>,
+}
+
+impl<T> Rk<T> {
+    pub fn new(value: T) -> Self {
+        let inner = Boks::ne(RkInner {
+            strong: Cell::new(1),
+            weak: Cell::new(1),
+            value: ManuallyDrop::new(value),
+        });
+        Rk {
+            p: Boks::into_non_null(inner),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Builds a value that holds a `Weak` to itself.
+    ///
+    /// The `Weak` passed to `data_fn` can't be upgraded until `new_cyclic` returns.
+    pub fn new_cyclic<F>(data_fn: F) -> Self
+    where
+        F: FnOnce(&Weak<T>) -> T,
+    {
+        let p = Boks::into_non_null(Boks::<RkInner<T>>::new_uninit()).cast::<RkInner<T>>();
+        // SAFETY: p is valid for writes, only the counts are initialised here and
+        // nothing reads the value while strong is 0.
+        unsafe {
+            (&raw mut (*p.as_ptr()).strong).write(Cell::new(0));
+            (&raw mut (*p.as_ptr()).weak).write(Cell::new(1));
+        }
+        // If data_fn panics, dropping this frees the memory without touching the
+        // value.
+        let weak = Weak { p };
+        let value = data_fn(&weak);
+        // SAFETY: as above, and the value is initialised before strong becomes 1.
+        unsafe { (&raw mut (*p.as_ptr()).value).write(ManuallyDrop::new(value)) };
+        weak.strong().set(1);
+        // The Weak becomes the one all Rks share.
+        std::mem::forget(weak);
+        Rk {
+            p,
+            phantom: PhantomData,
+        }
+    }
+
+    /// A `Weak` pointing to the same value.
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        increment(&this.inner().weak);
+        Weak { p: this.p }
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        this.inner().strong.get()
+    }
+
+    pub fn weak_count(this: &Self) -> usize {
+        this.inner().weak.get() - 1
+    }
+
+    /// Whether both point to the same value, not just equal ones.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.p == other.p
+    }
+
+    /// A mutable reference to the value, if no other `Rk` or `Weak` points to it.
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if Rk::is_unique(this) {
+            // SAFETY: nobody else can reach the value, and &mut this keeps it that way.
+            Some(unsafe { &mut (*this.p.as_ptr()).value })
+        } else {
+            None
+        }
+    }
+
+    /// A mutable reference to the value, cloning it into a new `Rk` first if it
+    /// is shared. Any `Weak` pointing to the old one no longer sees this one.
+    pub fn make_mut(this: &mut Self) -> &mut T
+    where
+        T: Clone,
+    {
+        if !Rk::is_unique(this) {
+            *this = Rk::new(T::clone(this));
+        }
+        // SAFETY: this is the only pointer to the value now.
+        unsafe { &mut (*this.p.as_ptr()).value }
+    }
+
+    /// Moves the value out if this is the only `Rk`, otherwise gives it back.
+    /// Any `Weak` left can no longer be upgraded.
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if Rk::strong_count(&this) != 1 {
+            return Err(this);
+        }
+        let this = ManuallyDrop::new(this);
+        this.inner().strong.set(0);
+        // SAFETY: this was the last Rk and it is never dropped, so the value is
+        // moved out exactly once and never dropped in place.
+        let value = unsafe { ptr::read(&*this.inner().value) };
+        drop(Weak { p: this.p });
+        Ok(value)
+    }
+
+    fn is_unique(this: &Self) -> bool {
+        let inner = this.inner();
+        inner.strong.get() == 1 && inner.weak.get() == 1
+    }
+
+    fn inner(&self) -> &RkInner<T> {
+        // SAFETY: the memory stays allocated while there is an Rk, and the value
+        // stays initialised.
+        unsafe { self.p.as_ref() }
+    }
+
+    /// # Safety
+    ///
+    /// Only from drop, self is never used again.
+    unsafe fn drop_ref(&mut self) {
+        let strong = self.inner().strong.get() - 1;
+        self.inner().strong.set(strong);
+        if strong == 0 {
+            // Released even if the value's destructor panics.
+            let _weak = Weak { p: self.p };
+            // SAFETY: this was the last Rk, nobody can reach the value anymore and
+            // it has not been dropped before. Like Boks, this only runs T's own
+            // destructor and doesn't access T otherwise.
+            unsafe { ptr::drop_in_place((&raw mut (*self.p.as_ptr()).value).cast::<T>()) };
+        }
+    }
+}
+
+impl<T> Weak<T> {
+    /// An `Rk` to the value, if it hasn't been dropped yet.
+    pub fn upgrade(&self) -> Option<Rk<T>> {
+        let strong = self.strong().get();
+        if strong == 0 {
+            return None;
+        }
+        increment(self.strong());
+        Some(Rk {
+            p: self.p,
+            phantom: PhantomData,
+        })
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.strong().get()
+    }
+
+    // The value may be dropped already or not written yet, so unlike Rk this never
+    // makes a reference to the whole RkInner, only to the counts.
+
+    fn strong(&self) -> &Cell<usize> {
+        // SAFETY: the memory stays allocated while there is a Weak, and the counts
+        // are always initialised.
+        unsafe { &(*self.p.as_ptr()).strong }
+    }
+
+    fn weak(&self) -> &Cell<usize> {
+        // SAFETY: as above.
+        unsafe { &(*self.p.as_ptr()).weak }
+    }
+
+    /// # Safety
+    ///
+    /// Only from drop, self is never used again.
+    unsafe fn drop_ref(&mut self) {
+        let weak = self.weak().get() - 1;
+        self.weak().set(weak);
+        if weak == 0 {
+            // SAFETY: the memory came from Boks::into_non_null and this was the last
+            // pointer to it. The value is gone already, MaybeUninit makes sure the
+            // Boks only frees the memory.
+            drop(unsafe { Boks::from_non_null(self.p.cast::<MaybeUninit<RkInner<T>>>()) });
+        }
+    }
+}
+
+/// `#[may_dangle]` like on `Boks`: dropping an `Rk` at most drops the `T`, it never
+/// reads it, so what `T` borrows only has to outlive `T`'s own destructor.
+#[cfg(feature = "nightly")]
+unsafe impl<#[may_dangle] T> Drop for Rk<T> {
+    fn drop(&mut self) {
+        // SAFETY: this is drop, self is never used again
+        unsafe { self.drop_ref() }
+    }
+}
+
+/// Stable fallback without the eyepatch, see `Boks`.
+#[cfg(not(feature = "nightly"))]
+impl<T> Drop for Rk<T> {
+    fn drop(&mut self) {
+        // SAFETY: this is drop, self is never used again
+        unsafe { self.drop_ref() }
+    }
+}
+
+/// A `Weak` never drops the `T`, so with `#[may_dangle]` and no `PhantomOwns<T>`
+/// the drop checker doesn't care about `T` at all when it goes away.
+#[cfg(feature = "nightly")]
+unsafe impl<#[may_dangle] T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        // SAFETY: this is drop, self is never used again
+        unsafe { self.drop_ref() }
+    }
+}
+
+#[cfg(not(feature = "nightly"))]
+impl<T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        // SAFETY: this is drop, self is never used again
+        unsafe { self.drop_ref() }
+    }
+}
+
+impl<T> Clone for Rk<T> {
+    fn clone(&self) -> Self {
+        increment(&self.inner().strong);
+        Rk {
+            p: self.p,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        increment(self.weak());
+        Weak { p: self.p }
+    }
+}
+
+impl<T> Deref for Rk<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner().value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Rk<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("(Weak)")
+    }
+}
+
+/// Like `Rc`: the counts are only touched in ways that leave them consistent,
+/// so only the shared `T` matters. Not Send or Sync, the NonNull takes care of that.
+impl<T: RefUnwindSafe> UnwindSafe for Rk<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Oisann;
+    use crate::ledger::DropLedger;
+    use crate::live::{LiveSet, Tracked};
+    use crate::{assert_impl, assert_not_impl};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    assert_not_impl!(Rk<u8>: Send, Sync);
+    assert_not_impl!(Weak<u8>: Send, Sync);
+    assert_impl!(Rk<u8>: Unpin, UnwindSafe);
+
+    #[test]
+    fn last_rk_drops_the_value_once() {
+        let ledger = DropLedger::new();
+        let a = Rk::new(Oisann::with_ledger(1, &ledger, "shared"));
+        let b = a.clone();
+        assert_eq!(Rk::strong_count(&a), 2);
+        assert!(Rk::ptr_eq(&a, &b));
+        drop(a);
+        assert!(ledger.is_empty());
+        drop(b);
+        ledger.assert_dropped_once("shared");
+    }
+
+    #[test]
+    fn weak_upgrades_until_the_value_is_gone() {
+        let set = LiveSet::new();
+        let a = Rk::new(set.track(5));
+        let w = Rk::downgrade(&a);
+        assert_eq!(Rk::weak_count(&a), 1);
+        assert_eq!(**w.upgrade().unwrap(), 5);
+        drop(a);
+        assert_eq!(set.live(), 0);
+        assert_eq!(w.strong_count(), 0);
+        assert!(w.upgrade().is_none());
+        let w2 = w.clone();
+        drop(w);
+        assert!(w2.upgrade().is_none());
+    }
+
+    #[test]
+    fn get_mut_only_when_unique() {
+        let mut a = Rk::new(1);
+        *Rk::get_mut(&mut a).unwrap() += 1;
+        let b = a.clone();
+        assert!(Rk::get_mut(&mut a).is_none());
+        drop(b);
+        let w = Rk::downgrade(&a);
+        assert!(Rk::get_mut(&mut a).is_none());
+        drop(w);
+        assert_eq!(Rk::get_mut(&mut a), Some(&mut 2));
+    }
+
+    #[test]
+    fn make_mut_clones_when_shared() {
+        let set = LiveSet::new();
+        let mut a = Rk::new(set.track(1));
+        **Rk::make_mut(&mut a) += 1;
+        assert_eq!(set.created(), 1);
+
+        let b = a.clone();
+        let w = Rk::downgrade(&a);
+        **Rk::make_mut(&mut a) += 1;
+        assert_eq!((**a, **b), (3, 2));
+        assert!(!Rk::ptr_eq(&a, &b));
+        assert!(Rk::ptr_eq(&w.upgrade().unwrap(), &b));
+        assert_eq!(set.created(), 2);
+    }
+
+    #[test]
+    fn try_unwrap_moves_the_value_out() {
+        let set = LiveSet::new();
+        let a = Rk::new(set.track(1));
+        let b = a.clone();
+        let a = Rk::try_unwrap(a).unwrap_err();
+        drop(b);
+        let w = Rk::downgrade(&a);
+        let t = Rk::try_unwrap(a).unwrap();
+        assert!(w.upgrade().is_none());
+        assert_eq!(set.live(), 1);
+        drop(t);
+        assert_eq!(set.dropped(), 1);
+    }
+
+    struct Node {
+        next: RefCell<Option<Rk<Node>>>,
+        _tracked: Tracked<()>,
+    }
+
+    #[test]
+    #[should_panic(expected = "2 of 2 tracked values leaked")]
+    fn strong_cycles_leak() {
+        let set = LiveSet::new();
+        let a = Rk::new(Node {
+            next: RefCell::new(None),
+            _tracked: set.track_as("a", ()),
+        });
+        let b = Rk::new(Node {
+            next: RefCell::new(Some(a.clone())),
+            _tracked: set.track_as("b", ()),
+        });
+        *a.next.borrow_mut() = Some(b.clone());
+        // Each still has a strong count of 1 from the other.
+        drop(a);
+        drop(b);
+    }
+
+    #[test]
+    fn weak_back_edges_do_not_leak() {
+        struct Parent {
+            children: RefCell<Vec<Rk<Child>>>,
+            _tracked: Tracked<()>,
+        }
+        struct Child {
+            parent: Weak<Parent>,
+            _tracked: Tracked<()>,
+        }
+
+        let set = LiveSet::new();
+        let parent = Rk::new(Parent {
+            children: RefCell::new(Vec::new()),
+            _tracked: set.track_as("parent", ()),
+        });
+        let child = Rk::new(Child {
+            parent: Rk::downgrade(&parent),
+            _tracked: set.track_as("child", ()),
+        });
+        parent.children.borrow_mut().push(child.clone());
+        assert!(child.parent.upgrade().is_some());
+        drop(parent);
+        assert!(child.parent.upgrade().is_none());
+        drop(child);
+        set.assert_clean();
+    }
+
+    #[test]
+    fn new_cyclic_points_to_itself() {
+        struct Me {
+            me: Weak<Me>,
+            upgraded_early: bool,
+            _tracked: Tracked<()>,
+        }
+
+        let set = LiveSet::new();
+        let me = Rk::new_cyclic(|w| Me {
+            me: w.clone(),
+            upgraded_early: w.upgrade().is_some(),
+            _tracked: set.track(()),
+        });
+        assert!(!me.upgraded_early);
+        assert!(Rk::ptr_eq(&me.me.upgrade().unwrap(), &me));
+        assert_eq!((Rk::strong_count(&me), Rk::weak_count(&me)), (1, 1));
+        drop(me);
+        set.assert_clean();
+    }
+
+    #[test]
+    fn new_cyclic_panic_frees_the_memory() {
+        let kept = RefCell::new(None);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Rk::<Rc<u8>>::new_cyclic(|w| {
+                *kept.borrow_mut() = Some(w.clone());
+                panic!("no value");
+            })
+        }));
+        assert!(result.is_err());
+        assert!(kept.borrow().as_ref().unwrap().upgrade().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn rk_mutable_param() {
+        let mut y = 42;
+        let a = Rk::new(&mut y);
+        let _b = a.clone();
+        // Like try_mutable_param for Boks: neither Rk is used again, and dropping
+        // them won't read the &mut, so y is usable again.
+        y += 1;
+        assert_eq!(y, 43);
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn weak_may_outlive_what_t_borrows() {
+        let w;
+        {
+            let x = 1;
+            let a = Rk::new(Oisann::ne(&x));
+            w = Rk::downgrade(&a);
+            drop(a);
+            assert!(w.upgrade().is_none());
+        }
+        // w is only dropped here, after x, and all it does is free the memory.
+    }
+}
@@ -0,0 +1,161 @@
+//! Where an `Oisann` announces its drop.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::sync::mpsc::Sender;
+
+/// Receives the formatted value of an `Oisann` as it is dropped.
+///
+/// Called from inside `drop`, so there is nowhere to report a failure to: sinks
+/// swallow their own errors rather than panic during unwinding.
+pub trait DropSink {
+    fn announce(&mut self, value: fmt::Arguments<'_>);
+}
+
+/// Prints to stdout with `println!`, which the test harness captures per test.
+/// What `Oisann::ne` uses.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PrintSink;
+
+impl DropSink for PrintSink {
+    fn announce(&mut self, value: fmt::Arguments<'_>) {
+        println!("{value}");
+    }
+}
+
+/// Writes one line per drop, e.g. to `io::stderr()` or a log `File`.
+impl<W: io::Write> DropSink for W {
+    fn announce(&mut self, value: fmt::Arguments<'_>) {
+        let _ = writeln!(self, "{value}");
+    }
+}
+
+/// Sends each announcement down an mpsc channel.
+///
+/// A wrapper because `Sender` could start implementing `io::Write` upstream,
+/// which would overlap with the impl for writers.
+#[derive(Clone, Debug)]
+pub struct ChannelSink(pub Sender<String>);
+
+impl From<Sender<String>> for ChannelSink {
+    fn from(tx: Sender<String>) -> Self {
+        ChannelSink(tx)
+    }
+}
+
+impl DropSink for ChannelSink {
+    fn announce(&mut self, value: fmt::Arguments<'_>) {
+        // The receiver going away just means nobody is listening anymore.
+        let _ = self.0.send(value.to_string());
+    }
+}
+
+thread_local! {
+    static BUFFER: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Collects announcements in a buffer owned by the thread the drop runs on.
+///
+/// Handy in tests that run in parallel, each test thread only sees its own drops.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ThreadLocalSink;
+
+impl ThreadLocalSink {
+    /// Empties the current thread's buffer and returns what was in it.
+    pub fn take() -> Vec<String> {
+        BUFFER.with_borrow_mut(std::mem::take)
+    }
+}
+
+impl DropSink for ThreadLocalSink {
+    fn announce(&mut self, value: fmt::Arguments<'_>) {
+        // A drop during thread teardown may run after the buffer is gone.
+        let _ = BUFFER.try_with(|b| b.borrow_mut().push(value.to_string()));
+    }
+}
+
+/// Throws every announcement away.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct NoopSink;
+
+impl DropSink for NoopSink {
+    fn announce(&mut self, _: fmt::Arguments<'_>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Boks, Oisann};
+    use std::fs;
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    /// A writer the test can still read from after the Oisann owning it is gone.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writer_gets_one_line_per_drop() {
+        let buf = SharedBuf::default();
+        drop(Oisann::with_sink(42, buf.clone()));
+        drop(Oisann::with_sink("hei", buf.clone()));
+        assert_eq!(*buf.0.lock().unwrap(), b"42\n\"hei\"\n");
+    }
+
+    #[test]
+    fn file_sink() {
+        let path = std::env::temp_dir().join(format!("oisann-{}.log", std::process::id()));
+        let file = fs::File::create(&path).unwrap();
+        drop(Oisann::with_sink(vec![1, 2], file));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[1, 2]\n");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn channel_sink_across_threads() {
+        let (tx, rx) = mpsc::channel();
+        let o = Oisann::with_sink(7, ChannelSink::from(tx));
+        thread::spawn(move || drop(o)).join().unwrap();
+        assert_eq!(rx.recv().unwrap(), "7");
+
+        // Nobody listening is not an error.
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+        drop(Oisann::with_sink(8, ChannelSink(tx)));
+    }
+
+    #[test]
+    fn thread_local_sink_only_sees_own_thread() {
+        let b = Boks::ne(Oisann::with_sink(1, ThreadLocalSink));
+        thread::spawn(|| {
+            drop(Oisann::with_sink(2, ThreadLocalSink));
+            assert_eq!(ThreadLocalSink::take(), ["2"]);
+        })
+        .join()
+        .unwrap();
+        drop(b);
+        assert_eq!(ThreadLocalSink::take(), ["1"]);
+        assert!(ThreadLocalSink::take().is_empty());
+    }
+
+    #[test]
+    fn zero_sized_sinks_cost_nothing() {
+        // Only the value and the erased announce function take up space.
+        let bare = size_of::<u64>() + size_of::<fn()>();
+        assert_eq!(size_of::<Oisann<u64, NoopSink>>(), bare);
+        assert_eq!(size_of::<Oisann<u64>>(), bare);
+        drop(Oisann::with_sink(1, NoopSink));
+    }
+}
@@ -0,0 +1,115 @@
+//! Compile-time assertions about how a type varies over a lifetime.
+//!
+//! Each macro takes the type with the lifetime under test bound up front, e.g.
+//! `assert_covariant!(for<'a> Boks<&'a str>)`, and expands to a function that
+//! only type-checks if the subtyping it claims actually holds.
+//!
+//! Invariance can't be asserted the same way: a failed subtyping is a borrowck
+//! error, not something code can react to. `assert_invariant!` only checks the
+//! type is well formed, the invariance itself is pinned down by the snippets in
+//! `tests/ui/` that expect both `assert_covariant!` and `assert_contravariant!`
+//! to fail for the same type.
+
+/// Fails to compile unless the type can be shortened: a value of it with a
+/// longer lifetime must be usable where a shorter one is expected.
+#[macro_export]
+macro_rules! assert_covariant {
+    (for<$a:lifetime> $ty:ty) => {
+        const _: () = {
+            type Probe<$a> = $ty;
+            #[allow(dead_code)]
+            fn covariant<'long: 'short, 'short>(v: Probe<'long>) -> Probe<'short> {
+                v
+            }
+        };
+    };
+}
+
+/// Fails to compile unless the type can be lengthened: a value of it with a
+/// shorter lifetime must be usable where a longer one is expected, as for
+/// `fn(&'a T)`.
+#[macro_export]
+macro_rules! assert_contravariant {
+    (for<$a:lifetime> $ty:ty) => {
+        const _: () = {
+            type Probe<$a> = $ty;
+            #[allow(dead_code)]
+            fn contravariant<'long: 'short, 'short>(v: Probe<'short>) -> Probe<'long> {
+                v
+            }
+        };
+    };
+}
+
+/// Documents that the type neither shortens nor lengthens over the lifetime.
+///
+/// Only checks the type is well formed, see the module docs for why, so pair
+/// every use with a compile-fail snippet under `tests/ui/`.
+#[macro_export]
+macro_rules! assert_invariant {
+    (for<$a:lifetime> $ty:ty) => {
+        const _: () = {
+            type Probe<$a> = $ty;
+            #[allow(dead_code)]
+            fn invariant<'same>(v: Probe<'same>) -> Probe<'same> {
+                v
+            }
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::allocator::Global;
+    use crate::ark::{self, Ark};
+    use crate::canary::Canary;
+    use crate::live::Tracked;
+    use crate::rk::{self, Rk};
+    use crate::vek::{RawVek, Vek};
+    use crate::{Boks, Discard, Empty, Oisann, PanicOnDrop};
+    use std::cell::Cell;
+
+    // Boks owns its T through NonNull<T>, so it varies like T does.
+    assert_covariant!(for<'a> Boks<&'a i32>);
+    assert_contravariant!(for<'a> Boks<fn(&'a i32)>);
+    // See tests/ui/boks_cell_is_invariant.rs
+    assert_invariant!(for<'a> Boks<Cell<&'a i32>>);
+    assert_covariant!(for<'a> Boks<i32, &'a Global>);
+
+    // Empty only produces T through PhantomData<fn() -> T>, which varies like T.
+    assert_covariant!(for<'a> Empty<&'a i32>);
+    assert_contravariant!(for<'a> Empty<fn(&'a i32)>);
+    // See tests/ui/empty_cell_is_invariant.rs
+    assert_invariant!(for<'a> Empty<Cell<&'a i32>>);
+
+    // Discard only takes T through PhantomContravariant<T>, which is
+    // PhantomData<fn(*const T)> and flips T's variance.
+    assert_contravariant!(for<'a> Discard<&'a i32>);
+    assert_covariant!(for<'a> Discard<fn(&'a i32)>);
+    // See tests/ui/discard_cell_is_invariant.rs
+    assert_invariant!(for<'a> Discard<Cell<&'a i32>>);
+
+    // Oisann holds its T by value.
+    assert_covariant!(for<'a> Oisann<&'a i32>);
+    assert_covariant!(for<'a> Oisann<&'a mut i32>);
+    assert_contravariant!(for<'a> Oisann<fn(&'a i32)>);
+    // See tests/ui/oisann_mut_ref_is_invariant.rs
+    assert_invariant!(for<'a> Oisann<&'a mut &'a i32>);
+    // See tests/ui/oisann_cell_is_invariant.rs
+    assert_invariant!(for<'a> Oisann<Cell<&'a i32>>);
+
+    // So does PanicOnDrop.
+    assert_covariant!(for<'a> PanicOnDrop<&'a i32>);
+
+    // Like Boks, the pointers own or point to their T through NonNull.
+    assert_covariant!(for<'a> Rk<&'a i32>);
+    assert_covariant!(for<'a> rk::Weak<&'a i32>);
+    assert_covariant!(for<'a> Ark<&'a i32>);
+    assert_covariant!(for<'a> ark::Weak<&'a i32>);
+    assert_covariant!(for<'a> Vek<&'a i32>);
+    assert_covariant!(for<'a> RawVek<&'a i32>);
+
+    // The test helpers hold what they watch or wrap by value or by shared reference.
+    assert_covariant!(for<'a> Canary<'a>);
+    assert_covariant!(for<'a> Tracked<&'a i32>);
+}
@@ -0,0 +1,425 @@
+//! A growable vector, `Boks` for many values.
+
+use crate::Boks;
+use crate::marker::PhantomOwns;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::slice;
+
+/// The memory behind a `Vek`: room for `capacity` values of `T`, none of which it
+/// ever drops.
+///
+/// Allocating, growing and freeing all go through a `Boks<[MaybeUninit<T>]>`, so
+/// zero-sized types and poisoning freed memory come for free.
+pub struct RawVek<T> {
+    buf: Boks<[MaybeUninit<T>]>,
+}
+
+impl<T> RawVek<T> {
+    /// No memory is allocated until something is reserved.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Zero-sized `T`s never need any memory, so there is always room for
+    /// `usize::MAX` of them, whatever `cap` asks for.
+    pub fn with_capacity(cap: usize) -> Self {
+        let cap = if size_of::<T>() == 0 { usize::MAX } else { cap };
+        RawVek {
+            buf: Boks::new_uninit_slice(cap),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        self.buf.as_ptr().cast()
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.buf.as_mut_ptr().cast()
+    }
+
+    /// Makes room for at least `additional` more values after the first `len`,
+    /// moving those `len` over if it has to reallocate. At least doubles the
+    /// capacity when it grows, so pushing one at a time stays cheap.
+    ///
+    /// Panics if `len > capacity`.
+    pub fn reserve(&mut self, len: usize, additional: usize) {
+        let cap = self.capacity();
+        assert!(
+            len <= cap,
+            "reserve len (is {len}) should be <= capacity (is {cap})"
+        );
+        if cap - len >= additional {
+            return;
+        }
+        let needed = len.checked_add(additional).expect("capacity overflow");
+        let mut buf = Boks::<T>::new_uninit_slice(needed.max(cap * 2).max(4));
+        // SAFETY: both are valid for len values, and they are different allocations.
+        unsafe { ptr::copy_nonoverlapping(self.buf.as_ptr(), buf.as_mut_ptr(), len) };
+        // The old memory is freed, and the values moved out of it are not dropped
+        // since it only holds MaybeUninit<T>s.
+        self.buf = buf;
+    }
+}
+
+impl<T> Default for RawVek<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A growable array of `T`s, like `Vec`.
+pub struct Vek<T> {
+    buf: RawVek<T>,
+    len: usize,
+    // The RawVek never drops a T, as far as the drop checker can tell from its
+    // MaybeUninit<T>s. This says Vek does, see Drop below.
+    phantom: PhantomOwns<T>,
+}
+
+impl<T> Vek<T> {
+    pub fn new() -> Self {
+        Vek {
+            buf: RawVek::new(),
+            len: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn with_capacity(cap: usize) -> Self {
+        Vek {
+            buf: RawVek::with_capacity(cap),
+            len: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Makes room for at least `additional` more values.
+    pub fn reserve(&mut self, additional: usize) {
+        self.buf.reserve(self.len, additional);
+    }
+
+    pub fn push(&mut self, t: T) {
+        self.reserve(1);
+        // SAFETY: there is room for at least one more value after len.
+        unsafe { self.buf.as_mut_ptr().add(self.len).write(t) };
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        // SAFETY: the value at the old last index is initialised, and with len
+        // lowered it won't be read or dropped through the Vek again.
+        Some(unsafe { self.buf.as_ptr().add(self.len).read() })
+    }
+
+    /// Inserts `t` at `index`, shifting everything after it one to the right.
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, t: T) {
+        let len = self.len;
+        assert!(
+            index <= len,
+            "insertion index (is {index}) should be <= len (is {len})"
+        );
+        self.reserve(1);
+        let p = self.buf.as_mut_ptr();
+        // SAFETY: there is room for one more, so shifting the len - index values
+        // from index up by one stays in bounds, and index is free to write after.
+        unsafe {
+            ptr::copy(p.add(index), p.add(index + 1), len - index);
+            p.add(index).write(t);
+        }
+        self.len += 1;
+    }
+
+    /// Removes and returns the value at `index`, shifting everything after it one
+    /// to the left.
+    ///
+    /// Panics if `index >= len`.
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len;
+        assert!(
+            index < len,
+            "removal index (is {index}) should be < len (is {len})"
+        );
+        let p = self.buf.as_mut_ptr();
+        // SAFETY: index is in bounds, and the value there is moved out before the
+        // ones after it are shifted over it.
+        let t = unsafe {
+            let t = p.add(index).read();
+            ptr::copy(p.add(index + 1), p.add(index), len - index - 1);
+            t
+        };
+        self.len -= 1;
+        t
+    }
+
+    /// Drops every value from `len` on, does nothing if there are not that many.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        // SAFETY: the values from len to self.len are initialised.
+        let tail = unsafe {
+            ptr::slice_from_raw_parts_mut(self.buf.as_mut_ptr().add(len), self.len - len)
+        };
+        // Lowered first: if a destructor panics, the rest of the tail is still
+        // dropped by drop_in_place, and none of it is dropped again by the Vek.
+        self.len = len;
+        // SAFETY: the tail is no longer part of the Vek.
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+}
+
+/// With `#[may_dangle]` like `Boks`: dropping a `Vek` drops its values and frees
+/// the memory, and reads none of them, so a `Vek<&mut i32>` may be dropped after
+/// the last use of the `i32`. A `Vek` of values with their own destructor, like
+/// `Oisann`, still keeps what they borrow alive through `PhantomOwns<T>`.
+#[cfg(feature = "nightly")]
+unsafe impl<#[may_dangle] T> Drop for Vek<T> {
+    fn drop(&mut self) {
+        // The RawVek frees the memory after, even if a destructor panics.
+        self.clear();
+    }
+}
+
+/// Stable fallback without the eyepatch, see `Boks`.
+#[cfg(not(feature = "nightly"))]
+impl<T> Drop for Vek<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Deref for Vek<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: the first len values are initialised.
+        unsafe { slice::from_raw_parts(self.buf.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for Vek<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        // SAFETY: the first len values are initialised, and &mut self makes this
+        // the only reference to them.
+        unsafe { slice::from_raw_parts_mut(self.buf.as_mut_ptr(), self.len) }
+    }
+}
+
+impl<T> Extend<T> for Vek<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        iter.for_each(|t| self.push(t));
+    }
+}
+
+impl<T> FromIterator<T> for Vek<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut v = Vek::new();
+        v.extend(iter);
+        v
+    }
+}
+
+impl<T> Default for Vek<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for Vek<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Vek<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: PartialEq> PartialEq for Vek<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Eq> Eq for Vek<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::DropLedger;
+    use crate::live::LiveSet;
+    use crate::{Oisann, PanicOnDrop};
+    use crate::{assert_impl, assert_not_impl};
+    use std::cell::Cell;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    assert_impl!(Vek<u8>: Send, Sync, Unpin);
+    assert_impl!(Vek<Cell<u8>>: Send);
+    assert_not_impl!(Vek<Rc<u8>>: Send, Sync);
+
+    #[test]
+    fn push_pop_insert_remove() {
+        let mut v = Vek::new();
+        assert_eq!(v.capacity(), 0);
+        v.push(1);
+        v.push(3);
+        v.insert(1, 2);
+        v.insert(3, 4);
+        v.insert(0, 0);
+        assert_eq!(*v, [0, 1, 2, 3, 4]);
+        assert_eq!(v.remove(0), 0);
+        assert_eq!(v.remove(3), 4);
+        assert_eq!(v.remove(1), 2);
+        assert_eq!(*v, [1, 3]);
+        assert_eq!(v.pop(), Some(3));
+        assert_eq!(v.pop(), Some(1));
+        assert_eq!(v.pop(), None);
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "insertion index (is 2) should be <= len (is 1)")]
+    fn insert_out_of_bounds() {
+        let mut v = Vek::new();
+        v.push(1);
+        v.insert(2, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "removal index (is 1) should be < len (is 1)")]
+    fn remove_out_of_bounds() {
+        let mut v = Vek::new();
+        v.push(1);
+        v.remove(1);
+    }
+
+    #[test]
+    fn growing_keeps_the_values() {
+        let mut v: Vek<String> = (0..100).map(|i| i.to_string()).collect();
+        assert!(v.capacity() >= 100);
+        v.extend(["a".to_owned(), "b".to_owned()]);
+        assert_eq!(v.len(), 102);
+        assert_eq!(v[42], "42");
+        assert_eq!(v[101], "b");
+        v[0].push('!');
+        assert_eq!(v.clone()[0], "0!");
+
+        let mut v = Vek::<u8>::with_capacity(10);
+        v.reserve(10);
+        assert_eq!(v.capacity(), 10);
+        v.reserve(11);
+        assert!(v.capacity() >= 20);
+    }
+
+    #[test]
+    fn zero_sized_values() {
+        let mut v = Vek::new();
+        assert_eq!(v.capacity(), usize::MAX);
+        for _ in 0..1000 {
+            v.push(());
+        }
+        v.insert(500, ());
+        v.remove(0);
+        assert_eq!(v.len(), 1000);
+        v.truncate(10);
+        assert_eq!(v.len(), 10);
+    }
+
+    #[test]
+    fn zero_sized_capacity_is_always_max() {
+        assert_eq!(RawVek::<()>::with_capacity(3).capacity(), usize::MAX);
+        assert_eq!(Vek::<()>::with_capacity(3).capacity(), usize::MAX);
+        assert_eq!(Vek::<u8>::with_capacity(3).capacity(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "reserve len (is 5) should be <= capacity (is 4)")]
+    fn reserve_past_capacity() {
+        RawVek::<u8>::with_capacity(4).reserve(5, 1);
+    }
+
+    #[test]
+    fn every_value_is_dropped_once() {
+        let set = LiveSet::new();
+        let mut v: Vek<_> = (0..10).map(|i| set.track(i)).collect();
+        v.truncate(8);
+        assert_eq!(set.dropped(), 2);
+        drop(v.remove(0));
+        drop(v.pop());
+        v.insert(0, set.track(10));
+        drop(v);
+        set.assert_clean();
+    }
+
+    #[test]
+    fn drops_front_to_back() {
+        let ledger = DropLedger::new();
+        let v: Vek<_> = (0..3)
+            .map(|i| Oisann::with_ledger(i, &ledger, format!("item-{i}")))
+            .collect();
+        drop(v);
+        ledger.assert_order(["item-0", "item-1", "item-2"]);
+    }
+
+    #[test]
+    fn panicking_element_still_drops_the_rest() {
+        let set = LiveSet::new();
+        let mut v = Vek::new();
+        v.push(PanicOnDrop::ne(set.track(0)));
+        v.push(PanicOnDrop::ne(set.track(1)));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| drop(v)));
+        assert!(result.is_err());
+        set.assert_clean();
+    }
+
+    #[test]
+    #[cfg(feature = "nightly")]
+    fn vek_mutable_param() {
+        let mut y = 42;
+        let mut z = 1;
+        let mut v = Vek::new();
+        v.push(&mut y);
+        v.push(&mut z);
+        *v[0] += 1;
+        // Like try_mutable_param for Boks: v is not used again and dropping it
+        // won't read the references, so y and z can be used again while it lives.
+        z += 1;
+        y += z;
+        assert_eq!(y, 45);
+    }
+}
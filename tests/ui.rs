@@ -0,0 +1,190 @@
+//! Compiles every snippet under `tests/ui/` against this crate with the local
+//! `rustc`, and checks it either compiles or fails with the expected errors.
+//!
+//! Each snippet starts with `//@` directives:
+//!
+//! - `//@ check-pass`: the snippet must compile.
+//! - `//@ error: E0505 E0597`: the snippet must fail with exactly these error codes,
+//!   and its stderr must match the `.stderr` file next to it. A bare `//@ error:`
+//!   expects only errors without a code, like "lifetime may not live long enough".
+//! - `//@ only-nightly` / `//@ only-stable`: only run with or without the
+//!   `nightly` feature.
+//!
+//! Run with `BLESS=1` to write the `.stderr` snapshots instead of comparing them.
+//! Paths in them are written as `$DIR` for `tests/ui/` and `$CRATE` for the rest
+//! of the crate, so they match on any checkout.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const NIGHTLY: bool = cfg!(feature = "nightly");
+
+enum Expect {
+    Pass,
+    Fail(BTreeSet<String>),
+}
+
+struct Snippet {
+    path: PathBuf,
+    expect: Expect,
+    skip: bool,
+}
+
+impl Snippet {
+    fn parse(path: PathBuf) -> Self {
+        let source = fs::read_to_string(&path).unwrap();
+        let mut expect = None;
+        let mut skip = false;
+        for directive in source.lines().filter_map(|l| l.strip_prefix("//@ ")) {
+            match directive.trim() {
+                "check-pass" => expect = Some(Expect::Pass),
+                "only-nightly" => skip |= !NIGHTLY,
+                "only-stable" => skip |= NIGHTLY,
+                other => match other.strip_prefix("error:") {
+                    Some(codes) => {
+                        let codes = codes.split_whitespace().map(String::from).collect();
+                        expect = Some(Expect::Fail(codes));
+                    }
+                    None => panic!("{}: unknown directive `{other}`", path.display()),
+                },
+            }
+        }
+        let expect = expect
+            .unwrap_or_else(|| panic!("{}: missing check-pass or error directive", path.display()));
+        Self { path, expect, skip }
+    }
+}
+
+fn rustc() -> Command {
+    Command::new(env::var_os("RUSTC").unwrap_or_else(|| "rustc".into()))
+}
+
+/// Builds this crate straight from source into `out`, with the same features as
+/// the test run. Going through rustc directly means the rlib always matches the
+/// toolchain the snippets are compiled with.
+fn build_crate(out: &Path) -> PathBuf {
+    let mut cmd = rustc();
+    cmd.args([
+        "--edition=2024",
+        "--crate-type=rlib",
+        "--crate-name=drop_check",
+    ])
+    .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("src/lib.rs"))
+    .arg("--out-dir")
+    .arg(out);
+    if NIGHTLY {
+        cmd.args(["--cfg", "feature=\"nightly\""]);
+    }
+    let output = cmd.output().unwrap();
+    assert!(
+        output.status.success(),
+        "building drop_check failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    out.join("libdrop_check.rlib")
+}
+
+fn compile(snippet: &Path, rlib: &Path, out: &Path) -> Output {
+    rustc()
+        .args(["--edition=2024", "--emit=metadata", "-A", "unused"])
+        .arg("--extern")
+        .arg(format!("drop_check={}", rlib.display()))
+        .arg("--out-dir")
+        .arg(out)
+        .arg(snippet)
+        .output()
+        .unwrap()
+}
+
+/// Keeps snapshots independent of where the repository is checked out.
+fn normalize(stderr: &[u8], dir: &Path) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    // The snippet directory first, it is inside the crate.
+    let stderr = stderr
+        .replace(&dir.display().to_string(), "$DIR")
+        .replace(env!("CARGO_MANIFEST_DIR"), "$CRATE");
+    let mut lines: Vec<_> = stderr.lines().map(str::trim_end).collect();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    lines.join("\n") + "\n"
+}
+
+fn error_codes(stderr: &str) -> BTreeSet<String> {
+    stderr
+        .lines()
+        .filter_map(|l| l.strip_prefix("error[")?.split_once(']'))
+        .map(|(code, _)| code.to_string())
+        .collect()
+}
+
+/// Checks one snippet, returning a description of what went wrong if anything.
+fn check(snippet: &Snippet, rlib: &Path, out: &Path, bless: bool) -> Option<String> {
+    let output = compile(&snippet.path, rlib, out);
+    let dir = snippet.path.parent().unwrap();
+    let stderr = normalize(&output.stderr, dir);
+    match &snippet.expect {
+        Expect::Pass if output.status.success() => None,
+        Expect::Pass => Some(format!("expected to compile, but got:\n{stderr}")),
+        Expect::Fail(_) if output.status.success() => {
+            Some("expected to fail, but it compiled".to_string())
+        }
+        Expect::Fail(expected) => {
+            let found = error_codes(&stderr);
+            if &found != expected {
+                return Some(format!(
+                    "expected errors {expected:?}, found {found:?}:\n{stderr}"
+                ));
+            }
+            let snapshot = snippet.path.with_extension("stderr");
+            if bless {
+                fs::write(&snapshot, &stderr).unwrap();
+                return None;
+            }
+            match fs::read_to_string(&snapshot) {
+                Ok(expected) if expected == stderr => None,
+                Ok(expected) => Some(format!(
+                    "stderr differs from {}\n--- expected\n{expected}\n--- actual\n{stderr}",
+                    snapshot.display()
+                )),
+                Err(_) => Some(format!(
+                    "missing {}, run with BLESS=1 to create it:\n{stderr}",
+                    snapshot.display()
+                )),
+            }
+        }
+    }
+}
+
+#[test]
+fn ui() {
+    let bless = env::var_os("BLESS").is_some();
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join(if NIGHTLY {
+        "ui-nightly"
+    } else {
+        "ui-stable"
+    });
+    fs::create_dir_all(&out).unwrap();
+    let rlib = build_crate(&out);
+
+    let mut paths: Vec<_> = fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ui"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .collect();
+    paths.sort();
+
+    let mut failures = Vec::new();
+    for snippet in paths.into_iter().map(Snippet::parse) {
+        if snippet.skip {
+            continue;
+        }
+        if let Some(failure) = check(&snippet, &rlib, &out, bless) {
+            failures.push(format!("{}: {failure}", snippet.path.display()));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n\n"));
+}
@@ -0,0 +1,10 @@
+//@ error: E0277
+// Boks<Cell<u8>> is Send, an Ark<Cell<u8>> is not: its clones on other threads
+// would all get a &Cell<u8>, so T has to be Sync as well.
+use drop_check::ark::Ark;
+use drop_check::assert_impl;
+use std::cell::Cell;
+
+assert_impl!(Ark<Cell<u8>>: Send);
+
+fn main() {}
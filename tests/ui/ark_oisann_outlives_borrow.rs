@@ -0,0 +1,12 @@
+//@ error: E0597
+// But not when dropping the value reads the reference.
+use drop_check::Oisann;
+use drop_check::ark::Ark;
+
+fn main() {
+    let a;
+    {
+        let x = 42;
+        a = Ark::new(Oisann::ne(&x));
+    }
+}
@@ -0,0 +1,12 @@
+//@ only-nightly
+//@ check-pass
+// Same as for Boks and Rk, the eyepatch lets the Ark be dropped after x.
+use drop_check::ark::Ark;
+
+fn main() {
+    let a;
+    {
+        let x = 42;
+        a = Ark::new(&x);
+    }
+}
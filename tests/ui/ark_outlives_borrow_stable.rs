@@ -0,0 +1,12 @@
+//@ only-stable
+//@ error: E0597
+// The stable counterpart of ark_outlives_borrow: x must outlive the Ark.
+use drop_check::ark::Ark;
+
+fn main() {
+    let a;
+    {
+        let x = 42;
+        a = Ark::new(&x);
+    }
+}
@@ -0,0 +1,29 @@
+//@ only-nightly
+//@ error: E0505
+// #[may_dangle] on Drop for Boks only covers T: the destructor still frees the
+// memory through its allocator, so an allocator it borrows can't be dropped first.
+#![feature(allocator_api)]
+
+use drop_check::Boks;
+use drop_check::allocator::{AllocError, Allocator, Global};
+use std::alloc::Layout;
+use std::ptr::NonNull;
+
+// Not Copy, so dropping it really moves it.
+struct Arena;
+
+unsafe impl Allocator for Arena {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { Global.deallocate(ptr, layout) }
+    }
+}
+
+fn main() {
+    let arena = Arena;
+    let _b = Boks::new_in(42, &arena);
+    drop(arena);
+}
@@ -0,0 +1,27 @@
+//@ only-stable
+//@ error: E0505
+// The stable counterpart of boks_allocator_dropped_first, which fails the same
+// way: the destructor frees the memory through the allocator it borrows.
+use drop_check::Boks;
+use drop_check::allocator::{AllocError, Allocator, Global};
+use std::alloc::Layout;
+use std::ptr::NonNull;
+
+// Not Copy, so dropping it really moves it.
+struct Arena;
+
+unsafe impl Allocator for Arena {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { Global.deallocate(ptr, layout) }
+    }
+}
+
+fn main() {
+    let arena = Arena;
+    let _b = Boks::new_in(42, &arena);
+    drop(arena);
+}
@@ -0,0 +1,10 @@
+//@ error:
+// Cell<&'a i32> is invariant in 'a, so Boks<Cell<&'a i32>> can be neither
+// shortened nor lengthened and both assertions must fail.
+use drop_check::{Boks, assert_contravariant, assert_covariant};
+use std::cell::Cell;
+
+assert_covariant!(for<'a> Boks<Cell<&'a i32>>);
+assert_contravariant!(for<'a> Boks<Cell<&'a i32>>);
+
+fn main() {}
@@ -0,0 +1,17 @@
+//@ check-pass
+// NonNull<T> is covariant, so a Boks of a longer lived reference can be used
+// where a shorter lived one is expected, same as for Box.
+use drop_check::Boks;
+
+fn main() {
+    let s = String::from("hei");
+    let mut stdb1: Box<&str> = Box::new(&*s);
+    let stdb2: Box<&'static str> = Box::new("hello");
+    stdb1 = stdb2;
+    println!("{}", stdb1);
+
+    let mut b1: Boks<&str> = Boks::ne(&*s);
+    let b2: Boks<&'static str> = Boks::ne("hello");
+    b1 = b2;
+    println!("{}", *b1);
+}
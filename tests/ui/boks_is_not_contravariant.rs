@@ -0,0 +1,7 @@
+//@ error:
+// A Boks of a short lived reference can't stand in for a longer lived one.
+use drop_check::{Boks, assert_contravariant};
+
+assert_contravariant!(for<'a> Boks<&'a i32>);
+
+fn main() {}
@@ -0,0 +1,14 @@
+//@ only-nightly
+//@ check-pass
+// With #[may_dangle] Drop for Boks promises not to touch the &mut i32, so y can
+// be used again while the Boks is still alive. Box gets the same treatment.
+use drop_check::Boks;
+
+fn main() {
+    let mut y = 42;
+    let _stdb = Box::new(&mut y);
+    println!("{}", y);
+
+    let _b = Boks::ne(&mut y);
+    println!("{}", y);
+}
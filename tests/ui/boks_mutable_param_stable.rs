@@ -0,0 +1,11 @@
+//@ only-stable
+//@ error: E0502
+// Without the nightly feature Drop for Boks has no #[may_dangle], so the compiler
+// assumes it reads the &mut i32 and y stays borrowed until the Boks is dropped.
+use drop_check::Boks;
+
+fn main() {
+    let mut y = 42;
+    let _b = Boks::ne(&mut y);
+    println!("{}", y);
+}
@@ -0,0 +1,13 @@
+//@ only-stable
+//@ error: E0502
+// The stable counterpart of the may_dangle_only_covers_value_not_allocator unit
+// test: without #[may_dangle] even the T of a Boks in another allocator keeps y
+// borrowed until the Boks is dropped.
+use drop_check::Boks;
+use drop_check::allocator::Global;
+
+fn main() {
+    let mut y = 42;
+    let _b = Boks::new_in(&mut y, &Global);
+    assert_eq!(y, 42);
+}
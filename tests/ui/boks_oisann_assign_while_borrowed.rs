@@ -0,0 +1,10 @@
+//@ error: E0506
+// z can't be overwritten while the Boks still needs it to print on drop.
+use drop_check::{Boks, Oisann};
+
+fn main() {
+    let mut z = 42;
+    let _b = Boks::ne(Oisann::ne(&z));
+    z = 43;
+    println!("{}", z);
+}
@@ -0,0 +1,9 @@
+//@ error: E0505
+// z can't be moved out while the Boks still needs it to print on drop.
+use drop_check::{Boks, Oisann};
+
+fn main() {
+    let z = String::from("hei");
+    let _b = Boks::ne(Oisann::ne(&z));
+    drop(z);
+}
@@ -0,0 +1,10 @@
+//@ error: E0502
+// PhantomData<T> tells the drop checker Boks drops a T, so it looks into
+// Drop for Oisann and sees the &mut z being used, exactly like for Box.
+use drop_check::{Boks, Oisann};
+
+fn main() {
+    let mut z = 42;
+    let _b = Boks::ne(Oisann::ne(&mut z));
+    println!("{:?}", z);
+}
@@ -0,0 +1,12 @@
+//@ error: E0597
+// Same as boks_outlives_borrow, but Oisann reads the reference when dropped,
+// so x has to outlive the Boks.
+use drop_check::{Boks, Oisann};
+
+fn main() {
+    let b;
+    {
+        let x = 42;
+        b = Boks::ne(Oisann::ne(&x));
+    }
+}
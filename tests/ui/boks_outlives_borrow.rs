@@ -0,0 +1,13 @@
+//@ only-nightly
+//@ check-pass
+// A Boks holding a plain reference may be dropped after the referent, the
+// eyepatch guarantees the reference is not used by the destructor.
+use drop_check::Boks;
+
+fn main() {
+    let b;
+    {
+        let x = 42;
+        b = Boks::ne(&x);
+    }
+}
@@ -0,0 +1,13 @@
+//@ only-stable
+//@ error: E0597
+// The stable counterpart of boks_outlives_borrow: without #[may_dangle] the drop
+// checker has to assume Drop for Boks reads the &i32, so x must outlive b.
+use drop_check::Boks;
+
+fn main() {
+    let b;
+    {
+        let x = 42;
+        b = Boks::ne(&x);
+    }
+}
@@ -0,0 +1,8 @@
+//@ error: E0277
+// Boks is only Send when its T is, and Rc isn't.
+use drop_check::{Boks, assert_impl};
+use std::rc::Rc;
+
+assert_impl!(Boks<Rc<u8>>: Send);
+
+fn main() {}
@@ -0,0 +1,20 @@
+//@ only-stable
+//@ error: E0502
+// The stable counterpart of the boks_returned_from_scoped_thread_may_dangle unit
+// test: the Boks handed back by the thread keeps y borrowed until it is dropped.
+use drop_check::Boks;
+use std::thread;
+
+fn main() {
+    let mut y = 42;
+    let _b = thread::scope(|s| {
+        s.spawn(|| {
+            let mut b = Boks::ne(&mut y);
+            **b += 1;
+            b
+        })
+        .join()
+        .unwrap()
+    });
+    assert_eq!(y, 43);
+}
@@ -0,0 +1,10 @@
+//@ error: E0502
+// Oisann prints its payload on drop, so the &mut z inside is used when the Box
+// is dropped at the end of main, after the println!.
+use drop_check::Oisann;
+
+fn main() {
+    let mut z = 42;
+    let _b = Box::new(Oisann::ne(&mut z));
+    println!("{:?}", z);
+}
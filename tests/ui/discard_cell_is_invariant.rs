@@ -0,0 +1,10 @@
+//@ error:
+// Cell<&'a i32> is invariant in 'a, and flipping that for Discard leaves it
+// invariant, so both assertions must fail.
+use drop_check::{Discard, assert_contravariant, assert_covariant};
+use std::cell::Cell;
+
+assert_covariant!(for<'a> Discard<Cell<&'a i32>>);
+assert_contravariant!(for<'a> Discard<Cell<&'a i32>>);
+
+fn main() {}
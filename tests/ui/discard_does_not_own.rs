@@ -0,0 +1,16 @@
+//@ check-pass
+// Discard never holds a T either, so like Empty it may outlive whatever T borrows.
+use drop_check::{Discard, Oisann, discard};
+
+fn tie<'a>(_: &'a i32, d: Discard<Oisann<&'a i32>>) -> Discard<Oisann<&'a i32>> {
+    d
+}
+
+fn main() {
+    let d;
+    {
+        let x = 42;
+        d = tie(&x, discard());
+    }
+    // d is only dropped here, after x.
+}
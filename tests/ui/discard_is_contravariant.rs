@@ -0,0 +1,12 @@
+//@ check-pass
+// PhantomData<fn(T)> makes Discard contravariant in T: something that accepts
+// any &'a str can accept a &'static str.
+use drop_check::Discard;
+
+fn lengthen<'a>(d: Discard<&'a str>) -> Discard<&'static str> {
+    d
+}
+
+fn main() {
+    let _ = lengthen;
+}
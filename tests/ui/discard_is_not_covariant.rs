@@ -0,0 +1,8 @@
+//@ error:
+// A Discard that only accepts &'static str can't stand in for one accepting
+// shorter lived references.
+use drop_check::{Discard, assert_covariant};
+
+assert_covariant!(for<'a> Discard<&'a i32>);
+
+fn main() {}
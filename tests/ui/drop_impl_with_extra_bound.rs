@@ -0,0 +1,15 @@
+//@ error: E0367
+// A Drop impl can't be more specific than the type it is for, so a bound needed
+// to format T on drop would have to sit on the struct as well. Oisann keeps its
+// struct bound-free by checking those bounds in its constructors instead.
+use std::fmt::Debug;
+
+struct Traced<T>(T);
+
+impl<T: Debug> Drop for Traced<T> {
+    fn drop(&mut self) {
+        println!("{:?}", self.0);
+    }
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+//@ check-pass
+// Empty never holds a T, so the drop checker doesn't care whether what T
+// borrows is still alive when an Empty goes away, even if T has a Drop impl.
+use drop_check::{Empty, Oisann, empty};
+
+fn tie<'a>(_: &'a i32, e: Empty<Oisann<&'a i32>>) -> Empty<Oisann<&'a i32>> {
+    e
+}
+
+fn main() {
+    let e2;
+    {
+        let x = 42;
+        e2 = tie(&x, empty());
+    }
+    // e2 is only dropped here, after x.
+}
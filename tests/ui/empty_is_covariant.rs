@@ -0,0 +1,11 @@
+//@ check-pass
+// PhantomData<fn() -> T> keeps Empty covariant in T.
+use drop_check::Empty;
+
+fn shorten<'a>(e: Empty<&'static str>) -> Empty<&'a str> {
+    e
+}
+
+fn main() {
+    let _ = shorten;
+}
@@ -0,0 +1,9 @@
+//@ error: E0283
+// Empty<Rc<u8>> is Send because it only holds a PhantomData<fn() -> Rc<u8>>, so
+// asserting otherwise must fail.
+use drop_check::{Empty, assert_not_impl};
+use std::rc::Rc;
+
+assert_not_impl!(Empty<Rc<u8>>: Send);
+
+fn main() {}
@@ -0,0 +1,9 @@
+//@ error:
+// &'a mut T is invariant in T, so with 'a in both places Oisann<&'a mut &'a i32>
+// can be neither shortened nor lengthened and both assertions must fail.
+use drop_check::{Oisann, assert_contravariant, assert_covariant};
+
+assert_covariant!(for<'a> Oisann<&'a mut &'a i32>);
+assert_contravariant!(for<'a> Oisann<&'a mut &'a i32>);
+
+fn main() {}
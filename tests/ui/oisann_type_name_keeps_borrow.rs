@@ -0,0 +1,10 @@
+//@ error: E0502
+// TypeNameFormat never looks at the value, but the drop checker only sees the
+// Drop impl, which has no #[may_dangle], so the borrow must still be alive.
+use drop_check::Oisann;
+
+fn main() {
+    let mut z = 42;
+    let _o = Oisann::type_name(&mut z);
+    println!("{}", z);
+}
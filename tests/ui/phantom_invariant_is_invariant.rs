@@ -0,0 +1,9 @@
+//@ error:
+// PhantomInvariant<T> fixes T exactly, so neither assertion can hold.
+use drop_check::marker::PhantomInvariant;
+use drop_check::{assert_contravariant, assert_covariant};
+
+assert_covariant!(for<'a> PhantomInvariant<&'a i32>);
+assert_contravariant!(for<'a> PhantomInvariant<&'a i32>);
+
+fn main() {}
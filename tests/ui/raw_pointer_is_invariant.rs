@@ -0,0 +1,12 @@
+//@ error: E0597
+// Had Boks stored a *mut T it would be invariant, and the assignment below would
+// need s to live for 'static.
+struct RawBoks<T>(*mut T);
+
+fn main() {
+    let s = String::from("hei");
+    let mut b1: RawBoks<&str> = RawBoks(Box::into_raw(Box::new(&*s)));
+    let b2: RawBoks<&'static str> = RawBoks(Box::into_raw(Box::new("hello")));
+    b1 = b2;
+    let _ = b1.0;
+}
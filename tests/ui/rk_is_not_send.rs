@@ -0,0 +1,9 @@
+//@ error: E0277
+// The counts are plain Cells, so an Rk can't be sent to another thread even when
+// T could.
+use drop_check::assert_impl;
+use drop_check::rk::Rk;
+
+assert_impl!(Rk<u8>: Send);
+
+fn main() {}
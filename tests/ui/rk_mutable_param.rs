@@ -0,0 +1,12 @@
+//@ only-nightly
+//@ check-pass
+// Like boks_mutable_param: #[may_dangle] on Drop for Rk means no clone of the Rk
+// reads the &mut i32 when it goes away, so y can be used again.
+use drop_check::rk::Rk;
+
+fn main() {
+    let mut y = 42;
+    let a = Rk::new(&mut y);
+    let _b = a.clone();
+    println!("{}", y);
+}
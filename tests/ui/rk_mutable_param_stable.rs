@@ -0,0 +1,12 @@
+//@ only-stable
+//@ error: E0502
+// The stable counterpart of rk_mutable_param: without #[may_dangle] y stays
+// borrowed until the last clone of the Rk is dropped.
+use drop_check::rk::Rk;
+
+fn main() {
+    let mut y = 42;
+    let a = Rk::new(&mut y);
+    let _b = a.clone();
+    println!("{}", y);
+}
@@ -0,0 +1,13 @@
+//@ error: E0502
+// The last Rk to go drops the Oisann, which reads the &mut z, so z stays
+// borrowed for as long as any clone is alive.
+use drop_check::Oisann;
+use drop_check::rk::Rk;
+
+fn main() {
+    let mut z = 42;
+    let a = Rk::new(Oisann::ne(&mut z));
+    let _b = a.clone();
+    drop(a);
+    println!("{:?}", z);
+}
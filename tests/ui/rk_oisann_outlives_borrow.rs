@@ -0,0 +1,12 @@
+//@ error: E0597
+// But not when dropping the value reads the reference.
+use drop_check::Oisann;
+use drop_check::rk::Rk;
+
+fn main() {
+    let a;
+    {
+        let x = 42;
+        a = Rk::new(Oisann::ne(&x));
+    }
+}
@@ -0,0 +1,12 @@
+//@ only-nightly
+//@ check-pass
+// An Rk holding a plain reference may be dropped after the referent.
+use drop_check::rk::Rk;
+
+fn main() {
+    let a;
+    {
+        let x = 42;
+        a = Rk::new(&x);
+    }
+}
@@ -0,0 +1,12 @@
+//@ only-stable
+//@ error: E0597
+// The stable counterpart of rk_outlives_borrow: x must outlive the Rk.
+use drop_check::rk::Rk;
+
+fn main() {
+    let a;
+    {
+        let x = 42;
+        a = Rk::new(&x);
+    }
+}
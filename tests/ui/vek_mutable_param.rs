@@ -0,0 +1,14 @@
+//@ only-nightly
+//@ check-pass
+// Like boks_mutable_param, for every element: Drop for Vek has #[may_dangle], so
+// the references it holds don't keep y and z borrowed until it is dropped.
+use drop_check::vek::Vek;
+
+fn main() {
+    let mut y = 42;
+    let mut z = 1;
+    let mut v = Vek::new();
+    v.push(&mut y);
+    v.push(&mut z);
+    println!("{} {}", y, z);
+}
@@ -0,0 +1,12 @@
+//@ only-stable
+//@ error: E0502
+// Without the nightly feature Drop for Vek has no #[may_dangle], so y stays
+// borrowed until the Vek is dropped.
+use drop_check::vek::Vek;
+
+fn main() {
+    let mut y = 42;
+    let mut v = Vek::new();
+    v.push(&mut y);
+    println!("{}", y);
+}
@@ -0,0 +1,12 @@
+//@ error: E0502
+// PhantomOwns<T> tells the drop checker the Vek drops its Oisanns, which read the
+// &mut z, exactly like for Vec.
+use drop_check::Oisann;
+use drop_check::vek::Vek;
+
+fn main() {
+    let mut z = 42;
+    let mut v = Vek::new();
+    v.push(Oisann::ne(&mut z));
+    println!("{:?}", z);
+}
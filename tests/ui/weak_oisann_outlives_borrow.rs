@@ -0,0 +1,15 @@
+//@ only-nightly
+//@ check-pass
+// A Weak never drops the value, so even a Weak to an Oisann that reads x when
+// dropped may outlive x.
+use drop_check::Oisann;
+use drop_check::rk::{Rk, Weak};
+
+fn main() {
+    let w: Weak<Oisann<&i32>>;
+    {
+        let x = 42;
+        let a = Rk::new(Oisann::ne(&x));
+        w = Rk::downgrade(&a);
+    }
+}
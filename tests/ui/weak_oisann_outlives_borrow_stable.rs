@@ -0,0 +1,14 @@
+//@ only-stable
+//@ error: E0597
+// Without #[may_dangle] the drop checker has to assume Drop for Weak reads the T.
+use drop_check::Oisann;
+use drop_check::rk::{Rk, Weak};
+
+fn main() {
+    let w: Weak<Oisann<&i32>>;
+    {
+        let x = 42;
+        let a = Rk::new(Oisann::ne(&x));
+        w = Rk::downgrade(&a);
+    }
+}